@@ -1,33 +1,79 @@
+mod breakdown;
 mod data;
+mod export;
 mod file_directory;
+mod html;
+mod ignore_rules;
+mod import;
+mod keymap;
+mod options;
+mod report;
+mod scan_error;
+mod search;
+mod sort;
 mod thread_pool;
 mod traits;
+mod trash;
+mod usage;
 mod utils;
+mod watcher;
 
+use breakdown::{Breakdown, Grouping};
 use data::TableColors;
-use file_directory::FileDirectory;
+pub use export::{ExportFormat, export_to_path};
+use file_directory::{Exclusion, FileDirectory, HiddenTotals, SizeMetric};
+pub use html::write_html_to_path;
+use ignore_rules::IgnoreRules;
+pub use import::import_ncdu_from_path;
+use keymap::{Action, Context, KEYMAP};
+pub use options::{IgnoreMode, Options};
 use ratatui::{
     DefaultTerminal, Frame,
-    crossterm::event::{self, Event, KeyCode, KeyModifiers},
-    layout::{Constraint, Layout, Rect},
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Flex, Layout, Rect},
     style::{Modifier, Style, Stylize, palette::tailwind},
     text::{Line, Span, Text},
-    widgets::{Block, BorderType, Cell, HighlightSpacing, Paragraph, Row, Table, TableState},
+    widgets::{
+        Block, BorderType, Cell, Clear, HighlightSpacing, Paragraph, Row, Table, TableState,
+    },
 };
+pub use report::{ReportMode, Units, print_report};
+use scan_error::ScanError;
+use search::{Search, SearchMode};
+use sort::SortOrder;
 use std::{
     io::Result,
+    path::PathBuf,
     sync::{
-        Arc,
-        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU32, Ordering},
     },
     thread::sleep,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 pub use thread_pool::ThreadPool;
 use traits::GetPhysicalSize;
+use trash::TrashedEntry;
+use usage::{UsageBase, UsageColumn};
 use utils::format_bytes;
+use watcher::Watcher;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Overlay {
+    Help,
+    Errors,
+}
+
+const MAX_FIND_RESULTS: usize = 1000;
+const LARGEST_FILES: usize = 100;
 
-const INFO_TEXT: [&str; 2] = ["[Esc: exit] - [q: back/quit] - [Enter: open]", "[h: help]"];
+struct Results {
+    title: String,
+    entries: Vec<Arc<FileDirectory>>,
+    state: TableState,
+}
+
+type TrashHistory = Arc<Mutex<Vec<(Arc<FileDirectory>, TrashedEntry)>>>;
 
 pub struct App {
     table_state: TableState,
@@ -35,12 +81,28 @@ pub struct App {
     cache_directory: Arc<FileDirectory>,
     directory: Arc<FileDirectory>,
     thread_pool: Arc<ThreadPool>,
+    options: Arc<Options>,
     scanning: bool,
     scanning_text: String,
     total_files: String,
     total_disk_usage: String,
+    total_errors: usize,
     path_in_progress: String,
     event_poll: Arc<AtomicBool>,
+    jobs: Arc<AtomicU32>,
+    confirm_delete: Option<Arc<FileDirectory>>,
+    overlay: Option<Overlay>,
+    overlay_scroll: u16,
+    search: Option<Search>,
+    search_prompt: bool,
+    results: Option<Results>,
+    breakdown: Option<Breakdown>,
+    sort_order: SortOrder,
+    size_metric: SizeMetric,
+    usage_column: UsageColumn,
+    usage_base: UsageBase,
+    trashed: TrashHistory,
+    notice: Arc<Mutex<String>>,
     colors: TableColors,
     update_tick: Instant,
     dirty: bool,
@@ -48,20 +110,44 @@ pub struct App {
 }
 
 impl App {
-    pub fn new(thread_pool: Arc<ThreadPool>, directory: Arc<FileDirectory>) -> Self {
+    pub fn new(
+        thread_pool: Arc<ThreadPool>,
+        options: Arc<Options>,
+        directory: Arc<FileDirectory>,
+    ) -> Self {
+        let size_metric = options.size_metric();
+        let usage_column = options.usage;
+        let usage_base = options.usage_relative;
+
         Self {
             table_state: TableState::default(),
             table: Table::default(),
             colors: TableColors::new(),
             thread_pool,
+            options,
             scanning: true,
             event_poll: Arc::new(AtomicBool::new(true)),
+            jobs: Arc::new(AtomicU32::new(0)),
+            confirm_delete: None,
+            overlay: None,
+            overlay_scroll: 0,
+            search: None,
+            search_prompt: false,
+            results: None,
+            breakdown: None,
+            sort_order: SortOrder::new(),
+            size_metric,
+            usage_column,
+            usage_base,
+            trashed: Arc::new(Mutex::new(Vec::new())),
+            notice: Arc::new(Mutex::new(String::from(""))),
             scanning_text: String::from("Scanning"),
             cache_directory: Arc::clone(&directory),
             directory: Arc::clone(&directory),
             total_files: String::from(""),
             path_in_progress: String::from(""),
             total_disk_usage: String::from(""),
+            total_errors: 0,
             update_tick: Instant::now(),
             dirty: true,
             exit: false,
@@ -73,13 +159,18 @@ impl App {
         self.table_state.select(Some(0));
 
         while !self.exit {
+            if let Some(warning) = self
+                .thread_pool
+                .watcher()
+                .and_then(|watcher| watcher.take_warning())
+            {
+                self.set_notice(warning);
+            }
             if self.scanning {
                 if self.thread_pool.active_count.load(Ordering::Relaxed) == 0 {
                     self.scanning = false;
-                    let event_poll = Arc::clone(&self.event_poll);
-                    self.thread_pool.execute(move || {
+                    self.run_in_background(|| {
                         sleep(Duration::from_secs(1));
-                        event_poll.store(false, Ordering::Relaxed);
                         Ok(())
                     });
                 }
@@ -109,56 +200,443 @@ impl App {
         ])
         .vertical_margin(1)
         .horizontal_margin(2);
-        let rects = vertical.split(frame.area().clone());
+        let rects = vertical.split(frame.area());
 
         self.render_total(frame, rects[0]);
         self.render_header(frame, rects[1]);
 
         self.render_table(frame, rects[2]);
 
-        if let Some(i) = self.table_state.selected() {
-            let text = self.directory.entries.lock().unwrap()[i].name.clone();
-            let p = Paragraph::new(format!(" Selected: [{text}]"))
-                .fg(tailwind::WHITE)
-                .bold();
-            frame.render_widget(p, rects[3]);
+        self.render_status(frame, rects[3]);
+        self.render_footer(frame, rects[4]);
+
+        if self.breakdown.is_some() {
+            self.render_breakdown(frame, rects[2]);
+        }
+        if self.results.is_some() {
+            self.render_results(frame, rects[2]);
         }
+        if self.confirm_delete.is_some() {
+            self.render_confirm_delete(frame, rects[2]);
+        }
+        if let Some(overlay) = self.overlay {
+            self.render_overlay(overlay, frame, rects[2]);
+        }
+    }
 
-        self.render_footer(frame, rects[4]);
+    fn context(&self) -> Context {
+        if self.confirm_delete.is_some() {
+            Context::Confirm
+        } else if self.overlay.is_some() {
+            Context::Overlay
+        } else if self.results.is_some() {
+            Context::Results
+        } else if self.breakdown.is_some() {
+            Context::Breakdown
+        } else if self.search_prompt {
+            Context::Search
+        } else {
+            Context::Browser
+        }
     }
 
     fn handle_events(&mut self) -> Result<()> {
-        if !self.event_poll.load(Ordering::Relaxed) || event::poll(Duration::from_millis(150))? {
+        let poll = self.options.watch || self.event_poll.load(Ordering::Relaxed);
+        if !poll || event::poll(Duration::from_millis(150))? {
             match event::read()? {
                 Event::Mouse(mouse) => match mouse.kind {
                     event::MouseEventKind::ScrollDown => self.next_row(),
                     event::MouseEventKind::ScrollUp => self.previous_row(),
                     _ => {}
                 },
-                Event::Key(key) => match key.code {
-                    KeyCode::Char('q') => self.back(),
-                    KeyCode::Char('h') => self.exit(),
-                    KeyCode::Enter => self.open_selected_dir(),
-                    KeyCode::Char('o') => self.open_selected_dir(),
-                    KeyCode::Down | KeyCode::Char('j') => self.next_row(),
-                    KeyCode::Up | KeyCode::Char('k') => self.previous_row(),
-                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        self.exit();
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    let context = self.context();
+                    if let Some(action) = keymap::action(context, key) {
+                        self.perform(action);
+                    } else if context == Context::Search
+                        && let KeyCode::Char(c) = key.code
+                        && !key.modifiers.contains(KeyModifiers::CONTROL)
+                        && let Some(search) = &mut self.search
+                    {
+                        search.push(c);
+                        self.select_first_match();
                     }
-                    _ => {}
-                },
+                }
                 _ => {}
             }
         }
         Ok(())
     }
 
+    fn perform(&mut self, action: Action) {
+        match action {
+            Action::Back => self.back(),
+            Action::Exit => self.exit(),
+            Action::ClearSearch if self.search.is_some() => self.search = None,
+            Action::ClearSearch => self.exit(),
+            Action::Open => self.open_selected_dir(),
+            Action::NextRow => self.next_row(),
+            Action::PreviousRow => self.previous_row(),
+            Action::Delete => self.confirm_delete_selected(),
+            Action::Trash => self.trash_selected(),
+            Action::Restore => self.restore_trashed(),
+            Action::Rescan => self.rescan(),
+            Action::Search => self.start_search(false),
+            Action::Find => self.start_search(true),
+            Action::LargestFiles => self.show_largest_files(),
+            Action::Breakdown => self.show_breakdown(),
+            Action::NextMatch => self.select_match(true),
+            Action::PreviousMatch => self.select_match(false),
+            Action::AcceptSearch => self.accept_search(),
+            Action::CancelSearch => {
+                self.search = None;
+                self.search_prompt = false;
+            }
+            Action::NextSearchMode => self.edit_search(Search::next_mode),
+            Action::ToggleSearchFilter => self.edit_search(Search::toggle_filter),
+            Action::DeleteSearchChar => self.edit_search(Search::pop),
+            Action::NextSortKey => self.sort_order.next_key(),
+            Action::ReverseSort => self.sort_order.reverse(),
+            Action::DirsFirst => self.sort_order.toggle_dirs_first(),
+            Action::ToggleSizeMetric => self.size_metric = self.size_metric.toggle(),
+            Action::NextUsageColumn => self.usage_column = self.usage_column.next(),
+            Action::ToggleUsageBase => self.usage_base = self.usage_base.toggle(),
+            Action::Export => self.export_tree(),
+            Action::Help => self.open_overlay(Overlay::Help),
+            Action::ShowErrors => self.open_overlay(Overlay::Errors),
+            Action::ScrollDown => self.overlay_scroll = self.overlay_scroll.saturating_add(1),
+            Action::ScrollUp => self.overlay_scroll = self.overlay_scroll.saturating_sub(1),
+            Action::CloseOverlay => self.overlay = None,
+            Action::NextResult => self.select_result(true),
+            Action::PreviousResult => self.select_result(false),
+            Action::OpenResult => self.open_result(),
+            Action::CloseResults => self.results = None,
+            Action::NextGroup => self.select_group(true),
+            Action::PreviousGroup => self.select_group(false),
+            Action::OpenGroup => self.open_group(),
+            Action::NextGrouping => {
+                if let Some(breakdown) = &mut self.breakdown {
+                    breakdown.toggle_grouping(self.size_metric);
+                }
+            }
+            Action::CloseBreakdown => self.breakdown = None,
+            Action::Confirm => self.delete_selected(),
+            Action::Cancel => self.confirm_delete = None,
+        }
+    }
+
     fn exit(&mut self) {
         self.exit = true;
     }
 
+    fn open_overlay(&mut self, overlay: Overlay) {
+        self.overlay = Some(overlay);
+        self.overlay_scroll = 0;
+    }
+
+    fn run_in_background<F>(&self, f: F)
+    where
+        F: FnOnce() -> Result<()> + Send + 'static,
+    {
+        let jobs = Arc::clone(&self.jobs);
+        let event_poll = Arc::clone(&self.event_poll);
+
+        jobs.fetch_add(1, Ordering::Relaxed);
+        event_poll.store(true, Ordering::Relaxed);
+
+        self.thread_pool.execute(move || {
+            let result = f();
+            if jobs.fetch_sub(1, Ordering::Relaxed) == 1 {
+                event_poll.store(false, Ordering::Relaxed);
+            }
+            result
+        });
+    }
+
+    fn set_notice(&self, text: String) {
+        *self.notice.lock().unwrap() = text;
+    }
+
+    fn is_visible(&self, entry: &FileDirectory) -> bool {
+        self.search
+            .as_ref()
+            .is_none_or(|search| !search.filter || search.global || search.is_match(&entry.name))
+    }
+
+    fn visible_position<P>(&self, predicate: P) -> Option<usize>
+    where
+        P: Fn(&FileDirectory) -> bool,
+    {
+        self.directory
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| self.is_visible(entry))
+            .position(|entry| predicate(entry))
+    }
+
+    fn visible_count(&self) -> usize {
+        self.directory
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| self.is_visible(entry))
+            .count()
+    }
+
+    fn selected_entry(&self) -> Option<Arc<FileDirectory>> {
+        let i = self.table_state.selected()?;
+        self.directory
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| self.is_visible(entry))
+            .nth(i)
+            .map(Arc::clone)
+    }
+
+    fn start_search(&mut self, global: bool) {
+        let mode = self
+            .search
+            .as_ref()
+            .map_or(SearchMode::Substring, |search| search.mode);
+        self.search = Some(Search::new(mode, global));
+        self.search_prompt = true;
+    }
+
+    fn accept_search(&mut self) {
+        self.search_prompt = false;
+        let Some(search) = self.search.take_if(|search| search.global) else {
+            return;
+        };
+        if !search.is_active() {
+            return;
+        }
+
+        let entries =
+            self.cache_directory
+                .largest_matching(self.size_metric, MAX_FIND_RESULTS, |entry| {
+                    search.is_match(&entry.name)
+                });
+        if entries.is_empty() {
+            self.set_notice(format!("No match for {}", search.query));
+            return;
+        }
+        let title = if entries.len() == MAX_FIND_RESULTS {
+            format!(" Largest {} matches for {} ", entries.len(), search.query)
+        } else {
+            format!(" {} matches for {} ", entries.len(), search.query)
+        };
+        self.results = Some(Results {
+            title,
+            entries,
+            state: TableState::default().with_selected(0),
+        });
+    }
+
+    fn show_largest_files(&mut self) {
+        let entries = self
+            .cache_directory
+            .largest_files(self.size_metric, LARGEST_FILES);
+        if entries.is_empty() {
+            self.set_notice(String::from("No files scanned yet"));
+            return;
+        }
+        self.results = Some(Results {
+            title: format!(" Largest {} files ", entries.len()),
+            entries,
+            state: TableState::default().with_selected(0),
+        });
+    }
+
+    fn show_breakdown(&mut self) {
+        let breakdown = Breakdown::new(
+            Arc::clone(&self.directory),
+            self.breakdown
+                .as_ref()
+                .map_or(Grouping::Extension, |breakdown| breakdown.grouping),
+            self.size_metric,
+        );
+        if breakdown.groups.is_empty() {
+            self.set_notice(String::from("No files below this directory"));
+            return;
+        }
+        self.breakdown = Some(breakdown);
+    }
+
+    fn select_group(&mut self, forward: bool) {
+        if let Some(breakdown) = &mut self.breakdown {
+            step_selection(&mut breakdown.state, breakdown.groups.len(), forward);
+        }
+    }
+
+    fn open_group(&mut self) {
+        let Some(breakdown) = &self.breakdown else {
+            return;
+        };
+        let Some(group) = breakdown.selected() else {
+            return;
+        };
+        let grouping = breakdown.grouping;
+        let entries =
+            breakdown
+                .root
+                .largest_matching(self.size_metric, MAX_FIND_RESULTS, |entry| {
+                    grouping.contains(&group.name, entry)
+                });
+        let title = if (entries.len() as u64) < group.count {
+            format!(
+                " Largest {} of {} {} files ",
+                entries.len(),
+                group.count,
+                group.name
+            )
+        } else {
+            format!(" {} {} files ", entries.len(), group.name)
+        };
+        self.results = Some(Results {
+            title,
+            entries,
+            state: TableState::default().with_selected(0),
+        });
+    }
+
+    fn select_result(&mut self, forward: bool) {
+        if let Some(results) = &mut self.results {
+            step_selection(&mut results.state, results.entries.len(), forward);
+        }
+    }
+
+    fn open_result(&mut self) {
+        let Some(entry) = self
+            .results
+            .as_ref()
+            .and_then(|results| results.entries.get(results.state.selected()?))
+            .map(Arc::clone)
+        else {
+            return;
+        };
+        let Some(parent) = entry.parent.lock().unwrap().upgrade().filter(|parent| {
+            parent
+                .entries
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|a| Arc::ptr_eq(a, &entry))
+        }) else {
+            self.set_notice(format!("{} no longer exists", entry.path.display()));
+            return;
+        };
+
+        self.results = None;
+        self.breakdown = None;
+        self.search = None;
+        self.directory = parent;
+        self.dirty = true;
+        self.directory
+            .sort_entries(&self.sort_order, self.size_metric);
+        let idx = self.visible_position(|a| std::ptr::eq(a, &*entry));
+        self.table_state.select(idx);
+    }
+
+    fn edit_search(&mut self, edit: fn(&mut Search)) {
+        if let Some(search) = &mut self.search {
+            edit(search);
+            self.select_first_match();
+        }
+    }
+
+    fn select_first_match(&mut self) {
+        let Some(search) = self.search.as_ref().filter(|search| !search.global) else {
+            return;
+        };
+        let index = self.visible_position(|entry| search.is_match(&entry.name));
+        self.table_state.select(index.or(Some(0)));
+    }
+
+    fn select_match(&mut self, forward: bool) {
+        let Some(search) = self
+            .search
+            .as_ref()
+            .filter(|search| search.is_active() && !search.global)
+        else {
+            self.set_notice(String::from("No search: press / to start one"));
+            return;
+        };
+        let matches: Vec<usize> = self
+            .directory
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| self.is_visible(entry))
+            .enumerate()
+            .filter(|(_, entry)| search.is_match(&entry.name))
+            .map(|(i, _)| i)
+            .collect();
+        let selected = self.table_state.selected().unwrap_or_default();
+        let index = if forward {
+            matches.iter().find(|&&i| i > selected).or(matches.first())
+        } else {
+            matches
+                .iter()
+                .rev()
+                .find(|&&i| i < selected)
+                .or(matches.last())
+        };
+        match index.copied() {
+            Some(index) => self.table_state.select(Some(index)),
+            None => self.set_notice(format!("No match for {}", search.query)),
+        }
+    }
+
+    fn can_modify(&self) -> bool {
+        if self.options.read_only() {
+            self.set_notice(String::from("Read-only: browsing an imported scan"));
+            false
+        } else if self.scanning {
+            self.set_notice(String::from("Wait for the scan to finish first"));
+            false
+        } else if self.jobs.load(Ordering::Relaxed) > 0 {
+            self.set_notice(String::from("Another operation is still running"));
+            false
+        } else {
+            true
+        }
+    }
+
+    fn confirm_delete_selected(&mut self) {
+        if self.can_modify() {
+            self.confirm_delete = self.selected_entry();
+        }
+    }
+
+    fn delete_selected(&mut self) {
+        let Some(entry) = self.confirm_delete.take() else {
+            return;
+        };
+        let notice = Arc::clone(&self.notice);
+        let thread_pool = Arc::clone(&self.thread_pool);
+
+        self.set_notice(format!("Deleting {}...", entry.path.to_string_lossy()));
+        self.run_in_background(move || {
+            let path = entry.path.to_string_lossy().into_owned();
+            let result = entry.delete().map(|()| thread_pool.detach_entry(&entry));
+            *notice.lock().unwrap() = match &result {
+                Ok(()) => format!("Deleted {path}"),
+                Err(e) => format!("Failed to delete {path}: {e}"),
+            };
+            result
+        });
+    }
+
     fn back(&mut self) {
         let current_dir = Arc::clone(&self.directory);
+        self.search = None;
+        self.search_prompt = false;
 
         if let Some(parent) = current_dir.parent.lock().unwrap().upgrade() {
             self.directory = Arc::clone(&parent);
@@ -178,40 +656,155 @@ impl App {
         }
     }
 
-    fn next_row(&mut self) {
-        if let Some(selected) = self.table_state.selected() {
-            if selected < self.directory.entries.lock().unwrap().len() - 1 {
-                self.table_state.select_next();
+    fn export_tree(&mut self) {
+        if self.scanning {
+            self.set_notice(String::from("Wait for the scan to finish first"));
+            return;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let path = PathBuf::from(format!("rdu-{timestamp}.json"));
+        let directory = Arc::clone(&self.cache_directory);
+        let notice = Arc::clone(&self.notice);
+
+        self.set_notice(format!("Exporting to {}...", path.display()));
+        self.run_in_background(move || {
+            let result = export::export_to_path(&directory, &path, ExportFormat::Ncdu);
+            *notice.lock().unwrap() = match &result {
+                Ok(()) => format!("Exported to {}", path.display()),
+                Err(e) => format!("Failed to export to {}: {e}", path.display()),
+            };
+            result
+        });
+    }
+
+    fn trash_selected(&mut self) {
+        if !self.can_modify() {
+            return;
+        }
+        let Some(entry) = self.selected_entry() else {
+            return;
+        };
+        let notice = Arc::clone(&self.notice);
+        let trashed = Arc::clone(&self.trashed);
+        let thread_pool = Arc::clone(&self.thread_pool);
+
+        self.set_notice(format!("Trashing {}...", entry.path.to_string_lossy()));
+        self.run_in_background(move || {
+            let path = entry.path.to_string_lossy().into_owned();
+            match trash::trash(&entry.path) {
+                Ok(trashed_entry) => {
+                    thread_pool.detach_entry(&entry);
+                    trashed.lock().unwrap().push((entry, trashed_entry));
+                    *notice.lock().unwrap() = format!("Trashed {path} [u: restore]");
+                    Ok(())
+                }
+                Err(e) => {
+                    *notice.lock().unwrap() = format!("Failed to trash {path}: {e}");
+                    Err(e)
+                }
             }
+        });
+    }
+
+    fn restore_trashed(&mut self) {
+        if !self.can_modify() {
+            return;
         }
+        let Some((entry, trashed_entry)) = self.trashed.lock().unwrap().pop() else {
+            self.set_notice(String::from("Nothing to restore"));
+            return;
+        };
+        let notice = Arc::clone(&self.notice);
+        let trashed = Arc::clone(&self.trashed);
+        let thread_pool = Arc::clone(&self.thread_pool);
+        let root = Arc::clone(&self.cache_directory);
+
+        self.run_in_background(move || {
+            let path = entry.path.to_string_lossy().into_owned();
+            match trash::restore(&trashed_entry) {
+                Ok(()) => {
+                    let parent = entry
+                        .path
+                        .parent()
+                        .and_then(|parent| root.find_directory(parent));
+                    *notice.lock().unwrap() = match parent {
+                        Some(parent) => {
+                            thread_pool.attach_entry(&parent, &entry);
+                            format!("Restored {path}")
+                        }
+                        None => format!("Restored {path} outside the scanned tree"),
+                    };
+                    Ok(())
+                }
+                Err(e) => {
+                    *notice.lock().unwrap() = format!("Failed to restore {path}: {e}");
+                    trashed.lock().unwrap().push((entry, trashed_entry));
+                    Err(e)
+                }
+            }
+        });
     }
 
-    fn previous_row(&mut self) {
-        if let Some(selected) = self.table_state.selected() {
-            if selected > 0 {
-                self.table_state.select_previous();
+    fn rescan(&mut self) {
+        if !self.can_modify() {
+            return;
+        }
+        let directory = self
+            .selected_entry()
+            .filter(|entry| entry.is_dir && entry.exclusion.is_none())
+            .unwrap_or_else(|| Arc::clone(&self.directory));
+        if directory.path.as_os_str().is_empty() {
+            self.set_notice(String::from("Select a scanned path to rescan"));
+            return;
+        }
+
+        let thread_pool = Arc::clone(&self.thread_pool);
+        match thread_pool.rescan(Arc::clone(&self.options), &directory) {
+            Ok(()) => {
+                self.scanning = true;
+                self.event_poll.store(true, Ordering::Relaxed);
+                self.set_notice(format!("Rescanning {}", directory.path.display()));
             }
+            Err(e) => self.set_notice(format!(
+                "Failed to rescan {}: {e}",
+                directory.path.display()
+            )),
         }
     }
 
-    fn open_selected_dir(&mut self) {
-        let selected = self.table_state.selected();
+    fn next_row(&mut self) {
+        if let Some(selected) = self.table_state.selected()
+            && selected + 1 < self.visible_count()
+        {
+            self.table_state.select_next();
+        }
+    }
 
-        if let Some(i) = selected {
-            let current_dir = Arc::clone(&self.directory);
-            let entry = Arc::clone(&current_dir.entries.lock().unwrap()[i]);
+    fn previous_row(&mut self) {
+        if let Some(selected) = self.table_state.selected()
+            && selected > 0
+        {
+            self.table_state.select_previous();
+        }
+    }
 
-            if entry.is_dir {
-                self.directory = entry;
-                self.dirty = true;
-                self.table_state.select_first();
-            }
+    fn open_selected_dir(&mut self) {
+        if let Some(entry) = self.selected_entry()
+            && entry.is_dir
+        {
+            self.directory = entry;
+            self.search = None;
+            self.dirty = true;
+            self.table_state.select_first();
         }
     }
 
     fn render_total(&mut self, frame: &mut Frame, area: Rect) {
         let horizontal = &Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]);
-        let rects = horizontal.split(area.clone());
+        let rects = horizontal.split(area);
 
         if self.dirty {
             self.total_files = self
@@ -219,13 +812,20 @@ impl App {
                 .total_files
                 .load(Ordering::Relaxed)
                 .to_string();
-            self.total_disk_usage = utils::format_bytes(self.cache_directory.actual_size_bytes());
+            self.total_errors = self.thread_pool.errors.lock().unwrap().len();
+            self.total_disk_usage =
+                utils::format_bytes(self.cache_directory.size(self.size_metric));
         }
 
-        let text = Line::from(vec![
+        let mut text = Line::from(vec![
             Span::from("Total Scanned Files: "),
             Span::from(&self.total_files),
         ]);
+        if self.total_errors > 0 {
+            text.push_span(
+                Span::from(format!(" ({} errors)", self.total_errors)).fg(self.colors.notice_fg),
+            );
+        }
         let paragraph = Paragraph::new(text).bold();
         let block = Block::bordered()
             .border_type(BorderType::Rounded)
@@ -234,7 +834,7 @@ impl App {
         frame.render_widget(paragraph.block(block), rects[0]);
 
         let text = Line::from(vec![
-            Span::from("Total Disk Usage: "),
+            Span::from(format!("Total {}: ", self.size_metric.label())),
             Span::from(&self.total_disk_usage),
         ]);
         let paragraph = Paragraph::new(text).bold();
@@ -247,7 +847,10 @@ impl App {
 
     fn render_header(&mut self, frame: &mut Frame, area: Rect) {
         if !self.scanning {
-            self.scanning_text = String::from("Scanning Done");
+            self.scanning_text = match self.options.max_depth {
+                Some(max_depth) => format!("Scanning Done (max depth {max_depth})"),
+                None => String::from("Scanning Done"),
+            };
             self.path_in_progress = self.directory.path.to_string_lossy().into_owned();
         } else if self.dirty {
             if self.scanning_text.len() > 13 {
@@ -284,76 +887,467 @@ impl App {
                 .add_modifier(Modifier::REVERSED)
                 .fg(self.colors.selected_cell_style_fg);
 
-            let entries_len = self.directory.entries.lock().unwrap().len();
-            let total_size = format_bytes(self.directory.actual_size_bytes.load(Ordering::Relaxed));
-
-            let header = [
-                vec![Line::from(format!(" Name ({entries_len})"))],
-                vec![Line::from(format!("| Disk_Usage ({total_size})"))],
-                vec![Line::from("| Type")],
-            ]
-            .into_iter()
-            .map(Cell::from)
-            .collect::<Row>()
-            .style(header_style)
-            .top_margin(0)
-            .bottom_margin(0)
-            .height(1);
-
             let data = Arc::clone(&self.directory);
-            {
-                data.sort_entries_by_size_desc();
+            let selected = self.selected_entry();
+            data.sort_entries(&self.sort_order, self.size_metric);
+            let total_size = self.directory.size(self.size_metric);
+            let all_entries = data.entries.lock().unwrap();
+            let entries: Vec<_> = all_entries
+                .iter()
+                .filter(|entry| self.is_visible(entry))
+                .collect();
+            let entries_len = entries.len();
+            match self.table_state.selected() {
+                Some(_) if entries_len == 0 => self.table_state.select(None),
+                Some(i) if i >= entries_len => self.table_state.select(Some(entries_len - 1)),
+                None if entries_len > 0 => self.table_state.select_first(),
+                _ => {}
+            }
+            let size_label = self.size_metric.label().replace(' ', "_");
+
+            let total_len = all_entries.len();
+            let name_header = if entries_len == total_len {
+                format!(" Name ({entries_len})")
+            } else {
+                format!(" Name ({entries_len} of {total_len})")
+            };
+            let show_usage = self.usage_column != UsageColumn::Off;
+            let usage_base = match self.usage_base {
+                UsageBase::Parent => total_size,
+                UsageBase::Largest => all_entries
+                    .iter()
+                    .map(|entry| entry.size(self.size_metric))
+                    .max()
+                    .unwrap_or_default(),
+            };
+            let mut header = vec![
+                Line::from(name_header),
+                Line::from(format!("| {size_label} ({})", format_bytes(total_size))),
+            ];
+            if show_usage {
+                let label = format!("| Usage {}", self.usage_base.label());
+                if label.len() <= usize::from(self.usage_column.width()) {
+                    header.push(Line::from(label));
+                } else {
+                    header.push(Line::from("| Usage"));
+                }
+            }
+            header.push(Line::from("| Type"));
+            let header = header
+                .into_iter()
+                .map(Cell::from)
+                .collect::<Row>()
+                .style(header_style)
+                .top_margin(0)
+                .bottom_margin(0)
+                .height(1);
+
+            if let Some(selected) = selected {
+                let idx = entries
+                    .iter()
+                    .position(|&entry| Arc::ptr_eq(entry, &selected));
+                self.table_state.select(idx.or(self.table_state.selected()));
             }
-            let entries = data.entries.lock().unwrap();
 
-            let entries = entries.iter().map(|entry| {
-                let item = entry.array();
-                item.into_iter()
+            let rows = entries.iter().map(|entry| {
+                let item = entry.array(self.size_metric);
+                let mut cells: Vec<_> = item
+                    .into_iter()
                     .enumerate()
                     .map(|(i, content)| {
                         if i == 0 {
-                            let text = Text::from(vec![Line::from(format!(" {content}"))]);
-                            Cell::from(text)
+                            Cell::from(self.name_line(entry))
                         } else {
                             let text = Text::from(vec![Line::from(format!("| {content}"))]);
                             Cell::from(text)
                         }
                     })
-                    .collect::<Row>()
-                    .height(1)
+                    .collect();
+                if show_usage {
+                    let usage = self
+                        .usage_column
+                        .format(entry.size(self.size_metric), usage_base);
+                    cells.insert(2, Cell::from(usage));
+                }
+                Row::new(cells).height(1)
             });
 
-            let block = Block::bordered().border_style(Style::new().fg(self.colors.header_bg));
+            let mut block = Block::bordered()
+                .border_style(Style::new().fg(self.colors.header_bg))
+                .title_top(
+                    Line::from(format!(" Sort: {} ", self.sort_order.label())).right_aligned(),
+                );
+            if let Some(search) = &self.search {
+                block = block.title_bottom(self.search_line(search, &entries));
+            }
 
-            self.table = Table::new(
-                entries,
-                [
-                    Constraint::Fill(1),
-                    Constraint::Fill(1),
-                    Constraint::Max(14),
-                ],
-            )
-            .header(header)
+            let mut widths = vec![Constraint::Fill(1), Constraint::Fill(1)];
+            if show_usage {
+                widths.push(Constraint::Length(self.usage_column.width()));
+            }
+            widths.push(Constraint::Max(14));
+
+            self.table = Table::new(rows, widths)
+                .header(header)
+                .block(block)
+                .row_highlight_style(selected_row_style)
+                .column_highlight_style(selected_col_style)
+                .cell_highlight_style(selected_cell_style)
+                .highlight_spacing(HighlightSpacing::Always);
+        }
+
+        frame.render_stateful_widget(&self.table, area, &mut self.table_state);
+    }
+
+    fn name_line(&self, entry: &FileDirectory) -> Line<'static> {
+        let mut line = Line::from(format!(" {}", entry.marker()));
+        let ranges = self
+            .search
+            .as_ref()
+            .filter(|search| !search.global)
+            .map(|search| search.match_ranges(&entry.name))
+            .unwrap_or_default();
+
+        let mut start = 0;
+        for range in ranges {
+            line.push_span(Span::from(entry.name[start..range.start].to_string()));
+            line.push_span(
+                Span::from(entry.name[range.clone()].to_string())
+                    .fg(self.colors.search_match_fg)
+                    .underlined(),
+            );
+            start = range.end;
+        }
+        line.push_span(Span::from(entry.name[start..].to_string()));
+        line
+    }
+
+    fn search_line(&self, search: &Search, entries: &[&Arc<FileDirectory>]) -> Line<'static> {
+        let cursor = if self.search_prompt { "▏" } else { "" };
+        let matches = entries
+            .iter()
+            .filter(|entry| search.is_match(&entry.name))
+            .count();
+        let status = if search.invalid {
+            String::from("invalid pattern")
+        } else if search.global {
+            String::from("enter to search the whole tree")
+        } else if search.is_active() {
+            format!("{matches} matches")
+        } else {
+            String::from("type to search")
+        };
+        let view = if search.global {
+            "tree"
+        } else if search.filter {
+            "filter"
+        } else {
+            "find"
+        };
+
+        Line::from(format!(
+            " /{}{cursor} · {} · {view} · {status} ",
+            search.query,
+            search.mode.label()
+        ))
+        .fg(self.colors.search_match_fg)
+    }
+
+    fn render_status(&self, frame: &mut Frame, area: Rect) {
+        let horizontal = &Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]);
+        let rects = horizontal.split(area);
+
+        if let Some(entry) = self.selected_entry() {
+            let mut text = Line::from(format!(" Selected: [{}]", entry.name))
+                .fg(tailwind::WHITE)
+                .bold();
+            if let Some(error) = entry.error.lock().unwrap().as_ref() {
+                text.push_span(Span::from(format!(" {error}")).fg(self.colors.notice_fg));
+            }
+            let shared = entry.shared_size_bytes();
+            if shared > 0 {
+                text.push_span(Span::from(format!(
+                    " {} shared via hardlinks",
+                    format_bytes(shared)
+                )));
+            }
+            let ignored = entry.ignored_size_bytes();
+            if ignored > 0 {
+                text.push_span(Span::from(format!(" {} ignored", format_bytes(ignored))));
+            }
+            frame.render_widget(Paragraph::new(text), rects[0]);
+        }
+
+        let notice = self.notice.lock().unwrap();
+        let p = Paragraph::new(format!("{notice} "))
+            .fg(self.colors.notice_fg)
+            .right_aligned();
+        frame.render_widget(p, rects[1]);
+    }
+
+    fn render_confirm_delete(&self, frame: &mut Frame, area: Rect) {
+        let Some(entry) = &self.confirm_delete else {
+            return;
+        };
+
+        let text = Text::from(vec![
+            Line::from(vec![
+                Span::from("Path:  ").bold(),
+                Span::from(entry.path.to_string_lossy().into_owned()),
+            ]),
+            Line::from(vec![
+                Span::from("Size:  ").bold(),
+                Span::from(format!(
+                    "{} (apparent {})",
+                    format_bytes(entry.actual_size_bytes()),
+                    format_bytes(entry.apparent_size_bytes())
+                )),
+            ]),
+            Line::from(vec![
+                Span::from("Items: ").bold(),
+                Span::from(entry.item_count().to_string()),
+            ]),
+            Line::from(""),
+            Line::from(keymap::hints(Context::Confirm).join(" - ")).centered(),
+        ]);
+
+        let vertical = Layout::vertical([Constraint::Length(7)]).flex(Flex::Center);
+        let horizontal = Layout::horizontal([Constraint::Percentage(60)]).flex(Flex::Center);
+        let [area] = vertical.areas(area);
+        let [area] = horizontal.areas(area);
+
+        let block = Block::bordered()
+            .border_type(BorderType::Rounded)
+            .border_style(Style::new().fg(self.colors.dialog_border_color))
+            .title(" Delete permanently? ");
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(text).block(block), area);
+    }
+
+    fn render_results(&mut self, frame: &mut Frame, area: Rect) {
+        let Some(results) = &mut self.results else {
+            return;
+        };
+
+        let vertical = Layout::vertical([Constraint::Percentage(90)]).flex(Flex::Center);
+        let horizontal = Layout::horizontal([Constraint::Percentage(80)]).flex(Flex::Center);
+        let [area] = vertical.areas(area);
+        let [area] = horizontal.areas(area);
+
+        let rows = results.entries.iter().map(|entry| {
+            let suffix = if entry.is_dir { "/" } else { "" };
+            Row::new([
+                Cell::from(Line::from(format_bytes(entry.size(self.size_metric))).right_aligned()),
+                Cell::from(format!("{}{suffix}", entry.path.display())),
+            ])
+        });
+        let block = Block::bordered()
+            .border_type(BorderType::Rounded)
+            .border_style(Style::new().fg(self.colors.header_bg))
+            .title(results.title.clone())
+            .title_bottom(
+                Line::from(format!(" {} ", keymap::hints(Context::Results).join(" - ")))
+                    .right_aligned(),
+            );
+        let table = Table::new(rows, [Constraint::Length(12), Constraint::Fill(1)])
             .block(block)
-            .row_highlight_style(selected_row_style)
-            .column_highlight_style(selected_col_style)
-            .cell_highlight_style(selected_cell_style)
+            .row_highlight_style(
+                Style::default()
+                    .bg(self.colors.selected_row_style_bg)
+                    .bold()
+                    .fg(self.colors.selected_row_style_fg),
+            )
             .highlight_spacing(HighlightSpacing::Always);
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(table, area, &mut results.state);
+    }
+
+    fn render_breakdown(&mut self, frame: &mut Frame, area: Rect) {
+        let Some(breakdown) = &mut self.breakdown else {
+            return;
+        };
+
+        let vertical = Layout::vertical([Constraint::Percentage(90)]).flex(Flex::Center);
+        let horizontal = Layout::horizontal([Constraint::Percentage(70)]).flex(Flex::Center);
+        let [area] = vertical.areas(area);
+        let [area] = horizontal.areas(area);
+
+        let header = Row::new([
+            Cell::from(format!(" {}", breakdown.grouping.label())),
+            Cell::from(Line::from("files").right_aligned()),
+            Cell::from(Line::from("size").right_aligned()),
+            Cell::from(Line::from("share").right_aligned()),
+        ])
+        .style(
+            Style::default()
+                .fg(self.colors.header_fg)
+                .bold()
+                .bg(self.colors.header_bg),
+        );
+        let rows = breakdown.groups.iter().map(|group| {
+            let percent = if breakdown.total > 0 {
+                group.size as f64 * 100.0 / breakdown.total as f64
+            } else {
+                0.0
+            };
+            Row::new([
+                Cell::from(format!(" {}", group.name)),
+                Cell::from(Line::from(group.count.to_string()).right_aligned()),
+                Cell::from(Line::from(format_bytes(group.size)).right_aligned()),
+                Cell::from(Line::from(format!("{percent:.1}%")).right_aligned()),
+            ])
+        });
+        let name = if breakdown.root.path.as_os_str().is_empty() {
+            String::from("all paths")
+        } else {
+            breakdown.root.path.display().to_string()
+        };
+        let block = Block::bordered()
+            .border_type(BorderType::Rounded)
+            .border_style(Style::new().fg(self.colors.header_bg))
+            .title(format!(" Types in {name} "))
+            .title_bottom(
+                Line::from(format!(
+                    " {} ",
+                    keymap::hints(Context::Breakdown).join(" - ")
+                ))
+                .right_aligned(),
+            );
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(10),
+                Constraint::Length(12),
+                Constraint::Length(8),
+            ],
+        )
+        .header(header)
+        .block(block)
+        .row_highlight_style(
+            Style::default()
+                .bg(self.colors.selected_row_style_bg)
+                .bold()
+                .fg(self.colors.selected_row_style_fg),
+        )
+        .highlight_spacing(HighlightSpacing::Always);
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(table, area, &mut breakdown.state);
+    }
+
+    fn render_overlay(&mut self, overlay: Overlay, frame: &mut Frame, area: Rect) {
+        let (title, lines) = match overlay {
+            Overlay::Help => (" Help ", self.help_lines()),
+            Overlay::Errors => (" Scan Errors ", self.error_lines()),
+        };
+
+        let vertical = Layout::vertical([Constraint::Percentage(90)]).flex(Flex::Center);
+        let horizontal = Layout::horizontal([Constraint::Percentage(70)]).flex(Flex::Center);
+        let [area] = vertical.areas(area);
+        let [area] = horizontal.areas(area);
+
+        let max_scroll = (lines.len() as u16).saturating_sub(area.height.saturating_sub(2));
+        self.overlay_scroll = self.overlay_scroll.min(max_scroll);
+
+        let block = Block::bordered()
+            .border_type(BorderType::Rounded)
+            .border_style(Style::new().fg(self.colors.header_bg))
+            .title(title)
+            .title_bottom(
+                Line::from(format!(" {} ", keymap::hints(Context::Overlay).join(" - ")))
+                    .right_aligned(),
+            );
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(block)
+                .scroll((self.overlay_scroll, 0)),
+            area,
+        );
+    }
+
+    fn help_lines(&self) -> Vec<Line<'static>> {
+        let mut lines = Vec::new();
+        for context in Context::ALL {
+            if !lines.is_empty() {
+                lines.push(Line::from(""));
+            }
+            lines.push(
+                Line::from(context.title())
+                    .bold()
+                    .fg(self.colors.help_title_fg),
+            );
+            for binding in KEYMAP.iter().filter(|binding| binding.context == context) {
+                let keys = binding
+                    .keys
+                    .iter()
+                    .map(keymap::key_label)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                lines.push(Line::from(vec![
+                    Span::from(format!("  {keys:<16}")).bold(),
+                    Span::from(binding.description),
+                ]));
+            }
         }
+        lines
+    }
 
-        frame.render_stateful_widget(&self.table, area, &mut self.table_state);
+    fn error_lines(&self) -> Vec<Line<'static>> {
+        let errors = self.thread_pool.errors.lock().unwrap();
+        if errors.is_empty() {
+            return vec![Line::from(" No errors")];
+        }
+        errors
+            .iter()
+            .map(|(path, error)| {
+                Line::from(vec![
+                    Span::from(format!(" {error}: ")).fg(self.colors.notice_fg),
+                    Span::from(path.to_string_lossy().into_owned()),
+                ])
+            })
+            .collect()
+    }
+
+    fn footer_context(&self) -> Context {
+        if self.results.is_some() {
+            Context::Results
+        } else if self.breakdown.is_some() {
+            Context::Breakdown
+        } else if self.search_prompt {
+            Context::Search
+        } else {
+            Context::Browser
+        }
     }
 
     fn render_footer(&self, frame: &mut Frame, area: Rect) {
-        let info_footer = Paragraph::new(Text::from_iter(INFO_TEXT))
-            .style(Style::new().fg(self.colors.row_fg))
-            .centered()
-            .block(
-                Block::bordered()
-                    .border_type(BorderType::Double)
-                    .border_style(Style::new().fg(self.colors.header_bg)),
-            );
+        let width = area.width.saturating_sub(2) as usize;
+        let info_footer = Paragraph::new(Text::from_iter(keymap::hint_lines(
+            self.footer_context(),
+            width,
+        )))
+        .style(Style::new().fg(self.colors.row_fg))
+        .centered()
+        .block(
+            Block::bordered()
+                .border_type(BorderType::Double)
+                .border_style(Style::new().fg(self.colors.header_bg)),
+        );
 
         frame.render_widget(info_footer, area);
     }
 }
+
+fn step_selection(state: &mut TableState, len: usize, forward: bool) {
+    let selected = state.selected().unwrap_or_default();
+    if forward && selected + 1 < len {
+        state.select_next();
+    } else if !forward && selected > 0 {
+        state.select_previous();
+    }
+}
@@ -0,0 +1,165 @@
+use super::{FileDirectory, file_directory::SizeMetric};
+use ratatui::widgets::TableState;
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+const CATEGORIES: [(&str, &[&str]); 7] = [
+    (
+        "video",
+        &[
+            "avi", "flv", "m4v", "mkv", "mov", "mp4", "mpeg", "mpg", "webm", "wmv",
+        ],
+    ),
+    (
+        "audio",
+        &["aac", "flac", "m4a", "mp3", "ogg", "opus", "wav", "wma"],
+    ),
+    (
+        "image",
+        &[
+            "bmp", "cr2", "gif", "heic", "ico", "jpeg", "jpg", "nef", "png", "psd", "raw", "svg",
+            "tif", "tiff", "webp",
+        ],
+    ),
+    (
+        "archive",
+        &[
+            "7z", "bz2", "deb", "gz", "iso", "jar", "lz4", "lzma", "rar", "rpm", "tar", "tgz",
+            "whl", "xz", "zip", "zst",
+        ],
+    ),
+    (
+        "document",
+        &[
+            "csv", "doc", "docx", "epub", "md", "odp", "ods", "odt", "pdf", "ppt", "pptx", "rtf",
+            "txt", "xls", "xlsx",
+        ],
+    ),
+    (
+        "source",
+        &[
+            "c", "cc", "cpp", "cs", "css", "go", "h", "hpp", "html", "java", "js", "json", "jsx",
+            "kt", "lua", "php", "pl", "py", "rb", "rs", "sh", "sql", "swift", "toml", "ts", "tsx",
+            "xml", "yaml", "yml",
+        ],
+    ),
+    (
+        "object",
+        &[
+            "a", "class", "dll", "dylib", "exe", "ko", "lib", "o", "obj", "pyc", "rlib", "rmeta",
+            "so", "wasm",
+        ],
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    Extension,
+    Category,
+}
+
+impl Grouping {
+    pub fn toggle(self) -> Self {
+        match self {
+            Grouping::Extension => Grouping::Category,
+            Grouping::Category => Grouping::Extension,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Grouping::Extension => "extension",
+            Grouping::Category => "category",
+        }
+    }
+
+    pub fn group_of(self, name: &str) -> String {
+        let extension = Path::new(name)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        match (self, extension) {
+            (Grouping::Extension, Some(extension)) => extension,
+            (Grouping::Extension, None) => String::from("(none)"),
+            (Grouping::Category, extension) => extension
+                .and_then(|extension| {
+                    CATEGORIES
+                        .iter()
+                        .find(|(_, extensions)| extensions.contains(&extension.as_str()))
+                })
+                .map_or("other", |(category, _)| category)
+                .to_string(),
+        }
+    }
+
+    pub fn contains(self, group: &str, entry: &FileDirectory) -> bool {
+        is_counted(entry) && self.group_of(&entry.name) == group
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub name: String,
+    pub count: u64,
+    pub size: u64,
+}
+
+pub struct Breakdown {
+    pub root: Arc<FileDirectory>,
+    pub grouping: Grouping,
+    pub groups: Vec<Group>,
+    pub total: u64,
+    pub state: TableState,
+}
+
+impl Breakdown {
+    pub fn new(root: Arc<FileDirectory>, grouping: Grouping, metric: SizeMetric) -> Self {
+        let mut breakdown = Self {
+            root,
+            grouping,
+            groups: Vec::new(),
+            total: 0,
+            state: TableState::default(),
+        };
+        breakdown.update(metric);
+        breakdown
+    }
+
+    pub fn toggle_grouping(&mut self, metric: SizeMetric) {
+        self.grouping = self.grouping.toggle();
+        self.update(metric);
+    }
+
+    pub fn selected(&self) -> Option<&Group> {
+        self.groups.get(self.state.selected()?)
+    }
+
+    fn update(&mut self, metric: SizeMetric) {
+        let mut groups: HashMap<String, (u64, u64)> = HashMap::new();
+        let mut pending = vec![Arc::clone(&self.root)];
+        while let Some(directory) = pending.pop() {
+            for entry in directory.entries.lock().unwrap().iter() {
+                if entry.is_dir {
+                    pending.push(Arc::clone(entry));
+                } else if is_counted(entry) {
+                    let group = groups
+                        .entry(self.grouping.group_of(&entry.name))
+                        .or_default();
+                    group.0 += 1;
+                    group.1 += entry.size(metric);
+                }
+            }
+        }
+
+        self.groups = groups
+            .into_iter()
+            .map(|(name, (count, size))| Group { name, count, size })
+            .collect();
+        self.groups
+            .sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+        self.total = self.groups.iter().map(|group| group.size).sum();
+        self.state.select((!self.groups.is_empty()).then_some(0));
+    }
+}
+
+fn is_counted(entry: &FileDirectory) -> bool {
+    !entry.is_dir && entry.exclusion.is_none() && !entry.is_duplicate_link()
+}
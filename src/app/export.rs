@@ -0,0 +1,222 @@
+use super::{
+    FileDirectory, GetPhysicalSize, SizeMetric,
+    utils::{format_local_time, user_name},
+};
+use clap::ValueEnum;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Result, Write, stdout},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// ncdu-compatible JSON dump that rdu -f can browse
+    Ncdu,
+    /// One comma-separated row per entry
+    Csv,
+    /// One tab-separated row per entry
+    Tsv,
+}
+
+impl ExportFormat {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => ExportFormat::Csv,
+            Some("tsv") => ExportFormat::Tsv,
+            _ => ExportFormat::Ncdu,
+        }
+    }
+}
+
+pub fn export_to_path(root: &FileDirectory, path: &Path, format: ExportFormat) -> Result<()> {
+    if path == Path::new("-") {
+        export(root, BufWriter::new(stdout().lock()), format)
+    } else {
+        export(root, BufWriter::new(File::create(path)?), format)
+    }
+}
+
+pub fn export<W: Write>(root: &FileDirectory, writer: W, format: ExportFormat) -> Result<()> {
+    match format {
+        ExportFormat::Ncdu => export_ncdu(root, writer),
+        ExportFormat::Csv => export_table(root, writer, b','),
+        ExportFormat::Tsv => export_table(root, writer, b'\t'),
+    }
+}
+
+pub fn export_ncdu<W: Write>(root: &FileDirectory, mut writer: W) -> Result<()> {
+    let timestamp = unix_seconds(SystemTime::now()).unwrap_or_default();
+    write!(
+        writer,
+        r#"[1,2,{{"progname":"rdu","progver":"{}","timestamp":{timestamp}}},"#,
+        env!("CARGO_PKG_VERSION")
+    )?;
+    write_entry(&mut writer, root, None)?;
+    writeln!(writer, "]")?;
+    writer.flush()
+}
+
+fn write_entry<W: Write>(
+    writer: &mut W,
+    entry: &FileDirectory,
+    parent_dev: Option<u64>,
+) -> Result<()> {
+    let is_listed_dir = entry.is_dir && entry.exclusion.is_none();
+    if is_listed_dir {
+        write!(writer, "[")?;
+    }
+
+    // ncdu has no notion of a truncated directory, so whatever was counted
+    // below --max-depth is folded into the directory's own size.
+    let (hidden_size, hidden_apparent) = {
+        let hidden = entry.hidden.lock().unwrap();
+        (hidden.size, hidden.apparent)
+    };
+    write!(writer, "{{\"name\":")?;
+    serde_json::to_writer(&mut *writer, &entry.name)?;
+    write!(
+        writer,
+        ",\"asize\":{},\"dsize\":{}",
+        entry.apparent_size + hidden_apparent,
+        entry.get_physical_size()? + hidden_size
+    )?;
+    if let Some(dev) = entry.dev
+        && Some(dev) != parent_dev
+    {
+        write!(writer, ",\"dev\":{dev}")?;
+    }
+    if let Some(inode) = entry.inode {
+        write!(writer, ",\"ino\":{inode}")?;
+    }
+    if !entry.is_dir && entry.hardlink_count > 1 {
+        write!(writer, ",\"nlink\":{},\"hlnkc\":true", entry.hardlink_count)?;
+    }
+    if entry.is_symlink {
+        write!(writer, ",\"notreg\":true")?;
+    }
+    if entry.error.lock().unwrap().is_some() {
+        write!(writer, ",\"read_error\":true")?;
+    }
+    if let Some(exclusion) = entry.exclusion {
+        write!(writer, ",\"excluded\":\"{}\"", exclusion.ncdu_name())?;
+    }
+    if let Some(uid) = entry.owner {
+        write!(writer, ",\"uid\":{uid}")?;
+    }
+    if let Some(mtime) = entry.modified.and_then(unix_seconds) {
+        write!(writer, ",\"mtime\":{mtime}")?;
+    }
+    write!(writer, "}}")?;
+
+    if is_listed_dir {
+        let entries = entry.entries.lock().unwrap();
+        for child in entries.iter() {
+            writeln!(writer, ",")?;
+            write_entry(writer, child, entry.dev)?;
+        }
+        write!(writer, "]")?;
+    }
+    Ok(())
+}
+
+fn unix_seconds(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+const TABLE_COLUMNS: [&str; 9] = [
+    "path",
+    "depth",
+    "is_dir",
+    "type",
+    "apparent_size",
+    "disk_size",
+    "item_count",
+    "mtime",
+    "owner",
+];
+
+pub fn export_table<W: Write>(root: &FileDirectory, mut writer: W, separator: u8) -> Result<()> {
+    let separator = char::from(separator);
+    writeln!(writer, "{}", TABLE_COLUMNS.join(&separator.to_string()))?;
+
+    let mut owners = HashMap::new();
+    if root.path.as_os_str().is_empty() {
+        for entry in root.entries.lock().unwrap().iter() {
+            write_row(&mut writer, entry, 0, separator, &mut owners)?;
+        }
+    } else {
+        write_row(&mut writer, root, 0, separator, &mut owners)?;
+    }
+    writer.flush()
+}
+
+fn write_row<W: Write>(
+    writer: &mut W,
+    entry: &FileDirectory,
+    depth: usize,
+    separator: char,
+    owners: &mut HashMap<u32, String>,
+) -> Result<()> {
+    let owner = entry
+        .owner
+        .map(|uid| owners.entry(uid).or_insert_with(|| user_name(uid)).as_str())
+        .unwrap_or_default();
+    let fields = [
+        escape_field(&entry.path.to_string_lossy(), separator),
+        depth.to_string(),
+        entry.is_dir.to_string(),
+        entry.get_type(),
+        entry.size(SizeMetric::Apparent).to_string(),
+        entry.size(SizeMetric::DiskUsage).to_string(),
+        entry.item_count().to_string(),
+        entry.modified.map(format_local_time).unwrap_or_default(),
+        escape_field(owner, separator),
+    ];
+    writeln!(writer, "{}", fields.join(&separator.to_string()))?;
+
+    for child in entry.entries.lock().unwrap().iter() {
+        write_row(writer, child, depth + 1, separator, owners)?;
+    }
+    Ok(())
+}
+
+fn escape_field(field: &str, separator: char) -> String {
+    if separator == '\t' {
+        field
+            .replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+    } else if field.contains([separator, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields_are_quoted_only_when_needed() {
+        assert_eq!(escape_field("plain.txt", ','), "plain.txt");
+        assert_eq!(escape_field("a,b", ','), "\"a,b\"");
+        assert_eq!(escape_field("say \"hi\"", ','), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_field("two\nlines", ','), "\"two\nlines\"");
+        assert_eq!(escape_field("cr\r", ','), "\"cr\r\"");
+        assert_eq!(escape_field("tab\there", ','), "tab\there");
+    }
+
+    #[test]
+    fn tsv_fields_escape_control_characters() {
+        assert_eq!(escape_field("plain.txt", '\t'), "plain.txt");
+        assert_eq!(escape_field("a\tb", '\t'), "a\\tb");
+        assert_eq!(escape_field("two\nlines\r", '\t'), "two\\nlines\\r");
+        assert_eq!(escape_field("back\\slash", '\t'), "back\\\\slash");
+        assert_eq!(escape_field("a,\"b\"", '\t'), "a,\"b\"");
+    }
+}
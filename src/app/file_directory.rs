@@ -1,38 +1,157 @@
-use super::{GetPhysicalSize, format_bytes};
+use super::{GetPhysicalSize, ScanError, format_bytes, sort::SortOrder};
 use std::{
-    io::Result,
-    path::PathBuf,
+    cmp::{self, Reverse},
+    collections::BinaryHeap,
+    fs,
+    io::{Error, Result},
+    path::{Path, PathBuf},
     sync::{
         Arc, Mutex, Weak,
         atomic::{AtomicBool, AtomicU64, Ordering},
     },
+    time::SystemTime,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeMetric {
+    DiskUsage,
+    Apparent,
+}
+
+impl SizeMetric {
+    pub fn toggle(self) -> Self {
+        match self {
+            SizeMetric::DiskUsage => SizeMetric::Apparent,
+            SizeMetric::Apparent => SizeMetric::DiskUsage,
+        }
+    }
+    pub fn label(self) -> &'static str {
+        match self {
+            SizeMetric::DiskUsage => "Disk Usage",
+            SizeMetric::Apparent => "Apparent Size",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exclusion {
+    OtherFilesystem,
+    Pattern,
+    ExcludeFrom,
+    Ignored,
+}
+
+impl Exclusion {
+    pub fn label(self) -> &'static str {
+        match self {
+            Exclusion::OtherFilesystem => "mount",
+            Exclusion::Pattern => "excluded",
+            Exclusion::ExcludeFrom => "exclude-from",
+            Exclusion::Ignored => "ignored",
+        }
+    }
+    pub fn ncdu_name(self) -> &'static str {
+        match self {
+            Exclusion::OtherFilesystem => "otherfs",
+            Exclusion::Pattern | Exclusion::ExcludeFrom | Exclusion::Ignored => "pattern",
+        }
+    }
+    pub fn from_ncdu_name(name: &str) -> Option<Self> {
+        match name {
+            "otherfs" | "kernfs" => Some(Exclusion::OtherFilesystem),
+            "pattern" | "frmlink" => Some(Exclusion::Pattern),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct HiddenTotals {
+    pub size: u64,
+    pub apparent: u64,
+    pub items: u64,
+    pub files: u64,
+    pub errors: u64,
+    /// Hardlinks counted below the depth limit, as `(dev, inode)`
+    pub links: Vec<(Option<u64>, u64)>,
+}
+
+struct Ranked {
+    size: u64,
+    entry: Arc<FileDirectory>,
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.size
+            .cmp(&other.size)
+            .then_with(|| other.entry.path.cmp(&self.entry.path))
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct FileDirectory {
     pub name: String,
     pub actual_size_bytes: AtomicU64,
+    pub apparent_size_bytes: AtomicU64,
+    pub apparent_size: u64,
+    pub item_count: AtomicU64,
+    pub shared_size_bytes: AtomicU64,
+    pub ignored_size_bytes: AtomicU64,
+    pub error_count: AtomicU64,
+    pub error: Mutex<Option<ScanError>>,
     pub is_dir: bool,
     pub is_symlink: bool,
     pub is_hardlink: bool,
+    pub is_duplicate_link: AtomicBool,
+    pub is_ignored: bool,
+    pub truncated: bool,
+    pub hidden: Mutex<HiddenTotals>,
+    pub exclusion: Option<Exclusion>,
     pub path: PathBuf,
     pub dirty: AtomicBool,
     pub parent: Mutex<Weak<FileDirectory>>,
     pub blocks: Option<u64>,
     pub hardlink_count: u64,
+    pub dev: Option<u64>,
+    pub inode: Option<u64>,
+    pub modified: Option<SystemTime>,
+    pub owner: Option<u32>,
     pub entries: Mutex<Vec<Arc<FileDirectory>>>,
 }
 
 impl FileDirectory {
-    pub fn array(&self) -> [String; 3] {
+    pub fn array(&self, metric: SizeMetric) -> [String; 3] {
         [
-            self.name.clone(),
-            format_bytes(self.actual_size_bytes.load(Ordering::Relaxed)),
+            format!("{}{}", self.marker(), self.name),
+            format_bytes(self.size(metric)),
             self.get_type(),
         ]
     }
     pub fn get_type(&self) -> String {
-        if self.is_hardlink {
+        if let Some(exclusion) = self.exclusion {
+            String::from(exclusion.label())
+        } else if self.is_ignored {
+            String::from("ignored")
+        } else if self.truncated {
+            String::from("max depth")
+        } else if self.is_duplicate_link() {
+            format!("dup-link({})", self.hardlink_count)
+        } else if self.is_hardlink {
             format!("hardlink({})", self.hardlink_count)
         } else if self.is_symlink {
             String::from("symlink")
@@ -40,48 +159,245 @@ impl FileDirectory {
             String::from("-")
         }
     }
-    pub fn actual_size_bytes(&self) -> u64 {
-        if !self.dirty.load(Ordering::Relaxed) {
-            self.actual_size_bytes.load(Ordering::Relaxed)
+    pub fn marker(&self) -> &'static str {
+        if self.error.lock().unwrap().is_some() {
+            "! "
+        } else if self.error_count() > 0 {
+            ". "
         } else {
-            let total = AtomicU64::new(self.get_physical_size().unwrap());
+            "  "
+        }
+    }
+    pub fn set_error(&self, error: ScanError) {
+        *self.error.lock().unwrap() = Some(error);
+        self.prograte_dirty_up();
+    }
+    pub fn error_count(&self) -> u64 {
+        self.actual_size_bytes();
+        self.error_count.load(Ordering::Relaxed)
+    }
+    pub fn shared_size_bytes(&self) -> u64 {
+        self.actual_size_bytes();
+        self.shared_size_bytes.load(Ordering::Relaxed)
+    }
+    pub fn ignored_size_bytes(&self) -> u64 {
+        self.actual_size_bytes();
+        self.ignored_size_bytes.load(Ordering::Relaxed)
+    }
+    pub fn size(&self, metric: SizeMetric) -> u64 {
+        match metric {
+            SizeMetric::DiskUsage => self.actual_size_bytes(),
+            SizeMetric::Apparent => self.apparent_size_bytes(),
+        }
+    }
+    pub fn actual_size_bytes(&self) -> u64 {
+        if self.dirty.swap(false, Ordering::Relaxed) {
+            let hidden = self.hidden.lock().unwrap();
+            let total = AtomicU64::new(self.get_physical_size().unwrap() + hidden.size);
+            let apparent = AtomicU64::new(self.apparent_size + hidden.apparent);
+            let items = AtomicU64::new(hidden.items);
+            let errors =
+                AtomicU64::new(self.error.lock().unwrap().is_some() as u64 + hidden.errors);
+            drop(hidden);
             let entries = self.entries.lock().unwrap();
+            let shared = AtomicU64::new(if self.is_hardlink {
+                self.get_physical_size().unwrap()
+            } else {
+                0
+            });
+            let ignored = AtomicU64::new(if self.is_ignored {
+                self.get_physical_size().unwrap()
+            } else {
+                0
+            });
             for entry in &*entries {
+                items.fetch_add(1 + entry.item_count(), Ordering::Relaxed);
+                if entry.is_duplicate_link() {
+                    continue;
+                }
                 total.fetch_add(entry.actual_size_bytes(), Ordering::Relaxed);
+                apparent.fetch_add(entry.apparent_size_bytes(), Ordering::Relaxed);
+                shared.fetch_add(entry.shared_size_bytes(), Ordering::Relaxed);
+                ignored.fetch_add(entry.ignored_size_bytes(), Ordering::Relaxed);
+                errors.fetch_add(entry.error_count(), Ordering::Relaxed);
             }
-            let total_value = total.load(Ordering::Relaxed);
-            self.actual_size_bytes.store(total_value, Ordering::Relaxed);
-            self.dirty.store(false, Ordering::Relaxed);
-
-            total_value
+            self.actual_size_bytes
+                .store(total.load(Ordering::Relaxed), Ordering::Relaxed);
+            self.apparent_size_bytes
+                .store(apparent.load(Ordering::Relaxed), Ordering::Relaxed);
+            self.item_count
+                .store(items.load(Ordering::Relaxed), Ordering::Relaxed);
+            self.shared_size_bytes
+                .store(shared.load(Ordering::Relaxed), Ordering::Relaxed);
+            self.ignored_size_bytes
+                .store(ignored.load(Ordering::Relaxed), Ordering::Relaxed);
+            self.error_count
+                .store(errors.load(Ordering::Relaxed), Ordering::Relaxed);
         }
+        self.actual_size_bytes.load(Ordering::Relaxed)
+    }
+    pub fn apparent_size_bytes(&self) -> u64 {
+        self.actual_size_bytes();
+        self.apparent_size_bytes.load(Ordering::Relaxed)
     }
     pub fn add_entry(self: Arc<Self>, entry: Arc<FileDirectory>) -> Result<()> {
         let entry_size = entry.get_physical_size()?;
         entry.actual_size_bytes.store(entry_size, Ordering::Relaxed);
+        entry
+            .apparent_size_bytes
+            .store(entry.apparent_size, Ordering::Relaxed);
+        if entry.is_hardlink {
+            entry.shared_size_bytes.store(entry_size, Ordering::Relaxed);
+        }
+        if entry.is_ignored {
+            entry
+                .ignored_size_bytes
+                .store(entry_size, Ordering::Relaxed);
+        }
         self.entries.lock().unwrap().push(Arc::clone(&entry));
         self.prograte_dirty_up();
         Ok(())
     }
+    pub fn set_hidden(&self, totals: HiddenTotals) -> HiddenTotals {
+        let previous = std::mem::replace(&mut *self.hidden.lock().unwrap(), totals);
+        self.prograte_dirty_up();
+        previous
+    }
+    pub fn take_entries(&self) -> Vec<Arc<FileDirectory>> {
+        let entries = std::mem::take(&mut *self.entries.lock().unwrap());
+        *self.error.lock().unwrap() = None;
+        self.prograte_dirty_up();
+        entries
+    }
     fn prograte_dirty_up(&self) {
         self.dirty.store(true, Ordering::Relaxed);
+        let mut parent = self.parent.lock().unwrap().upgrade();
+        while let Some(directory) = parent {
+            directory.dirty.store(true, Ordering::Relaxed);
+            parent = directory.parent.lock().unwrap().upgrade();
+        }
+    }
+    pub fn is_duplicate_link(&self) -> bool {
+        self.is_duplicate_link.load(Ordering::Relaxed)
+    }
+    pub fn set_duplicate_link(&self, duplicate: bool) {
+        self.is_duplicate_link.store(duplicate, Ordering::Relaxed);
+        self.prograte_dirty_up();
+    }
+    pub fn item_count(&self) -> u64 {
+        self.actual_size_bytes();
+        self.item_count.load(Ordering::Relaxed)
+    }
+    pub fn delete(&self) -> Result<()> {
+        if let Some(exclusion) = self.exclusion {
+            return Err(Error::other(format!(
+                "{} was not scanned ({})",
+                self.path.display(),
+                exclusion.label()
+            )));
+        }
+        if let Some(mount) = self.find_mount() {
+            return Err(Error::other(format!(
+                "{} contains the mount point {}",
+                self.path.display(),
+                mount.display()
+            )));
+        }
+        if fs::symlink_metadata(&self.path)?.is_dir() {
+            fs::remove_dir_all(&self.path)
+        } else {
+            fs::remove_file(&self.path)
+        }
+    }
+    fn find_mount(&self) -> Option<PathBuf> {
+        self.entries.lock().unwrap().iter().find_map(|entry| {
+            if entry.exclusion == Some(Exclusion::OtherFilesystem) {
+                Some(entry.path.clone())
+            } else {
+                entry.find_mount()
+            }
+        })
+    }
+    pub fn detach(self: &Arc<Self>) {
         if let Some(parent) = self.parent.lock().unwrap().upgrade() {
+            parent
+                .entries
+                .lock()
+                .unwrap()
+                .retain(|entry| !Arc::ptr_eq(entry, self));
             parent.prograte_dirty_up();
         }
     }
-    pub fn sort_entries_by_size_desc(&self) {
-        self.entries
+    pub fn find_directory(self: &Arc<Self>, path: &Path) -> Option<Arc<Self>> {
+        if self.path == path {
+            return Some(Arc::clone(self));
+        }
+        let child = self
+            .entries
             .lock()
             .unwrap()
-            .sort_by(|a, b| b.actual_size_bytes().cmp(&a.actual_size_bytes()));
+            .iter()
+            .find(|entry| {
+                entry.is_dir && entry.exclusion.is_none() && path.starts_with(&entry.path)
+            })
+            .cloned()?;
+        child.find_directory(path)
     }
-    pub fn blocks(&self) -> u64 {
-        if let Some(blocks) = self.blocks {
-            blocks
-        } else {
-            0
+    pub fn attach(self: &Arc<Self>) {
+        if let Some(parent) = self.parent.lock().unwrap().upgrade() {
+            parent.entries.lock().unwrap().push(Arc::clone(self));
+            parent.prograte_dirty_up();
         }
     }
+    pub fn sort_entries(&self, order: &SortOrder, metric: SizeMetric) {
+        let mut entries = self.entries.lock().unwrap();
+        let mut keyed: Vec<_> = entries
+            .drain(..)
+            .map(|entry| (order.key(metric, &entry), entry))
+            .collect();
+        keyed.sort_by(|a, b| order.compare(a, b));
+        entries.extend(keyed.into_iter().map(|(_, entry)| entry));
+    }
+    pub fn largest_files(self: &Arc<Self>, metric: SizeMetric, count: usize) -> Vec<Arc<Self>> {
+        self.largest_matching(metric, count, |entry| {
+            !entry.is_dir && entry.exclusion.is_none()
+        })
+    }
+    pub fn largest_matching<F>(
+        self: &Arc<Self>,
+        metric: SizeMetric,
+        count: usize,
+        is_match: F,
+    ) -> Vec<Arc<Self>>
+    where
+        F: Fn(&FileDirectory) -> bool,
+    {
+        let mut heap = BinaryHeap::with_capacity(count + 1);
+        let mut pending = vec![Arc::clone(self)];
+        while let Some(directory) = pending.pop() {
+            for entry in directory.entries.lock().unwrap().iter() {
+                if entry.is_dir {
+                    pending.push(Arc::clone(entry));
+                }
+                if !entry.is_duplicate_link() && is_match(entry) {
+                    heap.push(Reverse(Ranked {
+                        size: entry.size(metric),
+                        entry: Arc::clone(entry),
+                    }));
+                    if heap.len() > count {
+                        heap.pop();
+                    }
+                }
+            }
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(ranked)| ranked.entry)
+            .collect()
+    }
+    pub fn blocks(&self) -> u64 {
+        self.blocks.unwrap_or_default()
+    }
 }
 
 #[cfg(unix)]
@@ -0,0 +1,171 @@
+use super::{FileDirectory, SizeMetric, format_bytes, sort::SortOrder, utils::format_local_time};
+use std::{
+    fs::File,
+    io::{BufWriter, Result, Write, stdout},
+    path::Path,
+    time::SystemTime,
+};
+
+const MAX_ENTRIES_PER_DIRECTORY: usize = 1000;
+
+const STYLE: &str = "
+body { font: 14px/1.5 ui-monospace, monospace; margin: 2em; color: #1e293b; }
+h1 { font-size: 1.25em; margin: 0; }
+p { color: #64748b; margin: 0 0 1em; }
+details, .file { margin-left: 1.5em; }
+body > details { margin-left: 0; }
+summary, .file { display: flex; gap: 1em; align-items: center; white-space: nowrap; }
+summary { cursor: pointer; }
+.file { padding-left: 1.1em; }
+.size { width: 7em; text-align: right; }
+.percent { width: 4.5em; text-align: right; color: #64748b; }
+.bar { width: 10em; height: 0.7em; background: #e2e8f0; border-radius: 2px; }
+.bar > span { display: block; height: 100%; background: #3b82f6; border-radius: 2px; }
+.type, .more { color: #64748b; }
+";
+
+pub fn write_html_to_path(root: &FileDirectory, path: &Path, metric: SizeMetric) -> Result<()> {
+    if path == Path::new("-") {
+        write_html(root, BufWriter::new(stdout().lock()), metric)
+    } else {
+        write_html(root, BufWriter::new(File::create(path)?), metric)
+    }
+}
+
+pub fn write_html<W: Write>(root: &FileDirectory, mut writer: W, metric: SizeMetric) -> Result<()> {
+    let title = if root.path.as_os_str().is_empty() {
+        String::from("rdu report")
+    } else {
+        format!("rdu report: {}", root.path.display())
+    };
+    let total = root.size(metric);
+
+    writeln!(writer, "<!DOCTYPE html>")?;
+    writeln!(
+        writer,
+        "<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">"
+    )?;
+    writeln!(writer, "<title>{}</title>", escape(&title))?;
+    writeln!(writer, "<style>{STYLE}</style>\n</head>\n<body>")?;
+    writeln!(writer, "<h1>{}</h1>", escape(&title))?;
+    writeln!(
+        writer,
+        "<p>{} {}, {} items, generated {}</p>",
+        format_bytes(total),
+        metric.label().to_lowercase(),
+        root.item_count(),
+        format_local_time(SystemTime::now())
+    )?;
+    write_node(&mut writer, root, total, metric, true)?;
+    writeln!(writer, "</body>\n</html>")?;
+    writer.flush()
+}
+
+fn write_node<W: Write>(
+    writer: &mut W,
+    entry: &FileDirectory,
+    parent_size: u64,
+    metric: SizeMetric,
+    open: bool,
+) -> Result<()> {
+    let size = entry.size(metric);
+    let name = if entry.path.as_os_str().is_empty() {
+        String::from("total")
+    } else if open {
+        entry.path.display().to_string()
+    } else {
+        entry.name.clone()
+    };
+
+    if !entry.is_dir {
+        // Duplicate links are left out of the parent's total, so they take no share of it.
+        let share = if entry.is_duplicate_link() { 0 } else { size };
+        write!(writer, "<div class=\"file\">")?;
+        write_columns(writer, size, share, parent_size, &name, &entry.get_type())?;
+        return writeln!(writer, "</div>");
+    }
+
+    write!(
+        writer,
+        "<details{}><summary>",
+        if open { " open" } else { "" }
+    )?;
+    write_columns(
+        writer,
+        size,
+        size,
+        parent_size,
+        &format!("{name}/"),
+        &entry.get_type(),
+    )?;
+    writeln!(writer, "</summary>")?;
+
+    entry.sort_entries(&SortOrder::new(), metric);
+    let entries = entry.entries.lock().unwrap().clone();
+    for child in entries.iter().take(MAX_ENTRIES_PER_DIRECTORY) {
+        write_node(writer, child, size, metric, false)?;
+    }
+    if let Some(rest) = entries
+        .get(MAX_ENTRIES_PER_DIRECTORY..)
+        .filter(|rest| !rest.is_empty())
+    {
+        let rest_size: u64 = rest
+            .iter()
+            .filter(|child| !child.is_duplicate_link())
+            .map(|child| child.size(metric))
+            .sum();
+        write!(writer, "<div class=\"file more\">")?;
+        write_columns(
+            writer,
+            rest_size,
+            rest_size,
+            size,
+            &format!("{} more entries", rest.len()),
+            "",
+        )?;
+        writeln!(writer, "</div>")?;
+    }
+    writeln!(writer, "</details>")
+}
+
+fn write_columns<W: Write>(
+    writer: &mut W,
+    size: u64,
+    share: u64,
+    parent_size: u64,
+    name: &str,
+    kind: &str,
+) -> Result<()> {
+    let percent = if parent_size > 0 {
+        (share as f64 * 100.0 / parent_size as f64).min(100.0)
+    } else {
+        0.0
+    };
+    write!(
+        writer,
+        "<span class=\"size\">{}</span><span class=\"percent\">{percent:.1}%</span>\
+         <span class=\"bar\"><span style=\"width:{percent:.1}%\"></span></span>\
+         <span>{}</span>",
+        format_bytes(size),
+        escape(name)
+    )?;
+    if !kind.is_empty() && kind != "-" {
+        write!(writer, "<span class=\"type\">{}</span>", escape(kind))?;
+    }
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,51 @@
+use ignore::{
+    Match,
+    gitignore::{Gitignore, GitignoreBuilder},
+};
+use std::{path::Path, sync::Arc};
+
+#[derive(Debug)]
+pub struct IgnoreRules {
+    matcher: Gitignore,
+    parent: Option<Arc<IgnoreRules>>,
+}
+
+impl IgnoreRules {
+    pub fn for_root(path: &Path) -> Option<Arc<Self>> {
+        let repository = path.ancestors().find(|dir| dir.join(".git").exists())?;
+        let ancestors: Vec<_> = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(repository))
+            .collect();
+
+        ancestors.into_iter().rev().fold(None, Self::child)
+    }
+
+    pub fn child(parent: Option<Arc<Self>>, dir: &Path) -> Option<Arc<Self>> {
+        let mut builder = GitignoreBuilder::new(dir);
+        let git_dir = dir.join(".git");
+        if git_dir.is_dir() {
+            builder.add(git_dir.join("info").join("exclude"));
+        }
+        builder.add(dir.join(".gitignore"));
+        builder.add(dir.join(".ignore"));
+
+        match builder.build() {
+            Ok(matcher) if !matcher.is_empty() => Some(Arc::new(Self { matcher, parent })),
+            _ => parent,
+        }
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut rules = Some(self);
+        while let Some(current) = rules {
+            match current.matcher.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => rules = current.parent.as_deref(),
+            }
+        }
+        false
+    }
+}
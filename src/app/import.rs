@@ -0,0 +1,308 @@
+use super::{Exclusion, FileDirectory, ScanError, ThreadPool};
+use serde::{
+    Deserialize, Deserializer,
+    de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor},
+};
+use std::{
+    collections::HashSet,
+    fmt,
+    fs::File,
+    io::{BufReader, Error, ErrorKind, Read, Result, stdin},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, Weak,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, UNIX_EPOCH},
+};
+
+#[derive(Deserialize)]
+struct Info {
+    name: String,
+    #[serde(default)]
+    asize: u64,
+    #[serde(default)]
+    dsize: u64,
+    dev: Option<u64>,
+    ino: Option<u64>,
+    nlink: Option<u64>,
+    #[serde(default)]
+    hlnkc: bool,
+    #[serde(default)]
+    notreg: bool,
+    #[serde(default)]
+    read_error: bool,
+    excluded: Option<String>,
+    uid: Option<u32>,
+    mtime: Option<u64>,
+}
+
+struct Context<'a> {
+    thread_pool: &'a ThreadPool,
+    hardlinks: HashSet<(Option<u64>, u64)>,
+}
+
+pub fn import_ncdu_from_path(path: &Path, thread_pool: &ThreadPool) -> Result<Arc<FileDirectory>> {
+    if path == Path::new("-") {
+        import_ncdu(BufReader::new(stdin().lock()), thread_pool)
+    } else {
+        import_ncdu(BufReader::new(File::open(path)?), thread_pool)
+    }
+}
+
+pub fn import_ncdu<R: Read>(reader: R, thread_pool: &ThreadPool) -> Result<Arc<FileDirectory>> {
+    let mut context = Context {
+        thread_pool,
+        hardlinks: HashSet::new(),
+    };
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    // Every directory level nests a JSON array, so deep trees need more than
+    // serde_json's default limit of 128; grow the stack instead.
+    deserializer.disable_recursion_limit();
+    let root = DumpSeed(&mut context)
+        .deserialize(serde_stacker::Deserializer::new(&mut deserializer))
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    deserializer
+        .end()
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    Ok(root)
+}
+
+struct DumpSeed<'a, 'b>(&'a mut Context<'b>);
+
+impl<'de> DeserializeSeed<'de> for DumpSeed<'_, '_> {
+    type Value = Arc<FileDirectory>;
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for DumpSeed<'_, '_> {
+    type Value = Arc<FileDirectory>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an ncdu JSON dump")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> std::result::Result<Self::Value, A::Error> {
+        let major: u64 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        if major != 1 {
+            return Err(de::Error::custom(format!(
+                "unsupported dump format version {major}"
+            )));
+        }
+        let _minor: IgnoredAny = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        let _metadata: IgnoredAny = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+
+        let root = seq
+            .next_element_seed(EntrySeed {
+                context: self.0,
+                parent: Weak::new(),
+                parent_path: Path::new(""),
+                parent_dev: None,
+            })?
+            .ok_or_else(|| de::Error::invalid_length(3, &"a root directory"))?;
+
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(root)
+    }
+}
+
+struct EntrySeed<'a, 'b, 'c> {
+    context: &'a mut Context<'b>,
+    parent: Weak<FileDirectory>,
+    parent_path: &'c Path,
+    parent_dev: Option<u64>,
+}
+
+impl EntrySeed<'_, '_, '_> {
+    fn node(&self, info: Info, is_dir: bool, is_duplicate_link: bool) -> Arc<FileDirectory> {
+        let path = if Path::new(&info.name).is_absolute() {
+            PathBuf::from(&info.name)
+        } else {
+            self.parent_path.join(&info.name)
+        };
+        let nlink = info.nlink.unwrap_or(if info.hlnkc { 2 } else { 1 });
+        let read_error = info.read_error;
+
+        let node = Arc::new(FileDirectory {
+            apparent_size: info.asize,
+            blocks: Some(info.dsize.div_ceil(512)),
+            hardlink_count: nlink,
+            dev: info.dev.or(self.parent_dev),
+            inode: info.ino,
+            modified: info
+                .mtime
+                .map(|mtime| UNIX_EPOCH + Duration::from_secs(mtime)),
+            owner: info.uid,
+            is_hardlink: !is_dir && nlink > 1,
+            is_duplicate_link: AtomicBool::new(is_duplicate_link),
+            exclusion: info.excluded.as_deref().and_then(Exclusion::from_ncdu_name),
+            is_symlink: info.notreg,
+            is_dir,
+            name: info.name,
+            parent: Mutex::new(self.parent.clone()),
+            path,
+            ..Default::default()
+        });
+
+        if read_error {
+            let error = ScanError::Io(String::from("read error"));
+            node.set_error(error.clone());
+            self.context
+                .thread_pool
+                .errors
+                .lock()
+                .unwrap()
+                .push((node.path.clone(), error));
+        }
+        node
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for EntrySeed<'_, '_, '_> {
+    type Value = Arc<FileDirectory>;
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for EntrySeed<'_, '_, '_> {
+    type Value = Arc<FileDirectory>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a file object or a directory array")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> std::result::Result<Self::Value, A::Error> {
+        let info = Info::deserialize(de::value::MapAccessDeserializer::new(map))?;
+
+        let nlink = info.nlink.unwrap_or(if info.hlnkc { 2 } else { 1 });
+        let is_duplicate_link = nlink > 1
+            && info.ino.is_some_and(|inode| {
+                !self
+                    .context
+                    .hardlinks
+                    .insert((info.dev.or(self.parent_dev), inode))
+            });
+
+        if info.excluded.is_none() {
+            self.context
+                .thread_pool
+                .total_files
+                .fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(self.node(info, false, is_duplicate_link))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> std::result::Result<Self::Value, A::Error> {
+        let info: Info = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let directory = self.node(info, true, false);
+
+        while let Some(entry) = seq.next_element_seed(EntrySeed {
+            context: &mut *self.context,
+            parent: Arc::downgrade(&directory),
+            parent_path: &directory.path,
+            parent_dev: directory.dev,
+        })? {
+            Arc::clone(&directory)
+                .add_entry(entry)
+                .map_err(de::Error::custom)?;
+        }
+        Ok(directory)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::export::export_ncdu;
+
+    const DUMP: &str = r#"[1,2,{"progname":"ncdu","progver":"2.3","timestamp":1700000000},
+[{"name":"/data","asize":4096,"dsize":4096,"dev":2049,"ino":2},
+{"name":"notes.txt","asize":1000,"dsize":1000,"ino":10,"uid":1000,"mtime":1690000000},
+{"name":"a","asize":4096,"dsize":4096,"ino":11,"nlink":2,"hlnkc":true},
+{"name":"b","asize":4096,"dsize":4096,"ino":11,"nlink":2,"hlnkc":true},
+{"name":"link","asize":7,"dsize":0,"ino":12,"notreg":true},
+{"name":"cache","asize":0,"dsize":0,"excluded":"pattern"},
+[{"name":"sub","asize":4096,"dsize":4096,"ino":13},
+{"name":"deep.bin","asize":8192,"dsize":8192,"ino":14}]]]
+"#;
+
+    fn round_trip(dump: &[u8]) -> String {
+        let thread_pool = ThreadPool::new(1);
+        let root = import_ncdu(dump, &thread_pool).unwrap();
+        let mut exported = Vec::new();
+        export_ncdu(&root, &mut exported).unwrap();
+        String::from_utf8(exported).unwrap()
+    }
+
+    fn body(dump: &str) -> &str {
+        &dump[dump.find("{\"name\"").unwrap() - 1..]
+    }
+
+    #[test]
+    fn ncdu_export_round_trips_through_import() {
+        let exported = round_trip(DUMP.as_bytes());
+        assert!(
+            exported.contains(r#"{"name":"/data","asize":4096,"dsize":4096,"dev":2049,"ino":2}"#)
+        );
+        assert!(exported.contains(r#""name":"notes.txt","asize":1000,"dsize":1024"#));
+        assert!(exported.contains(r#""uid":1000,"mtime":1690000000"#));
+        assert!(
+            exported.contains(
+                r#""name":"b","asize":4096,"dsize":4096,"ino":11,"nlink":2,"hlnkc":true"#
+            )
+        );
+        assert!(exported.contains(r#""name":"link","asize":7,"dsize":0,"ino":12,"notreg":true"#));
+        assert!(exported.contains(r#""excluded":"pattern""#));
+        assert!(exported.contains(r#"[{"name":"sub""#));
+
+        let again = round_trip(exported.as_bytes());
+        assert_eq!(body(&again), body(&exported));
+    }
+
+    #[test]
+    fn deep_dumps_are_not_limited_by_recursion() {
+        const DEPTH: usize = 500;
+        let mut dump = String::from(r#"[1,2,{"progname":"rdu"},"#);
+        for level in 0..DEPTH {
+            dump.push_str(&format!(
+                r#"[{{"name":"d{level}","asize":4096,"dsize":4096}},"#
+            ));
+        }
+        dump.push_str(r#"{"name":"leaf","asize":1,"dsize":512}"#);
+        dump.push_str(&"]".repeat(DEPTH + 1));
+
+        let thread_pool = ThreadPool::new(1);
+        let mut directory = import_ncdu(dump.as_bytes(), &thread_pool).unwrap();
+        assert_eq!(directory.item_count(), DEPTH as u64);
+        for _ in 1..DEPTH {
+            let child = Arc::clone(&directory.entries.lock().unwrap()[0]);
+            directory = child;
+        }
+        assert_eq!(directory.entries.lock().unwrap()[0].name, "leaf");
+    }
+}
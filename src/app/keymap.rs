@@ -0,0 +1,511 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Context {
+    Global,
+    Browser,
+    Search,
+    Results,
+    Breakdown,
+    Overlay,
+    Confirm,
+}
+
+impl Context {
+    pub const ALL: [Context; 7] = [
+        Context::Global,
+        Context::Browser,
+        Context::Search,
+        Context::Results,
+        Context::Breakdown,
+        Context::Overlay,
+        Context::Confirm,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Context::Global => "Anywhere",
+            Context::Browser => "Browser",
+            Context::Search => "Search prompt",
+            Context::Results => "Result list",
+            Context::Breakdown => "Type breakdown",
+            Context::Overlay => "Help and lists",
+            Context::Confirm => "Confirmation dialog",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Back,
+    Exit,
+    ClearSearch,
+    Open,
+    NextRow,
+    PreviousRow,
+    Delete,
+    Trash,
+    Restore,
+    Rescan,
+    Search,
+    Find,
+    LargestFiles,
+    Breakdown,
+    NextUsageColumn,
+    ToggleUsageBase,
+    NextGroup,
+    PreviousGroup,
+    OpenGroup,
+    NextGrouping,
+    CloseBreakdown,
+    NextResult,
+    PreviousResult,
+    OpenResult,
+    CloseResults,
+    NextMatch,
+    PreviousMatch,
+    AcceptSearch,
+    CancelSearch,
+    NextSearchMode,
+    ToggleSearchFilter,
+    DeleteSearchChar,
+    NextSortKey,
+    ReverseSort,
+    DirsFirst,
+    ToggleSizeMetric,
+    Export,
+    ShowErrors,
+    Help,
+    ScrollDown,
+    ScrollUp,
+    CloseOverlay,
+    Confirm,
+    Cancel,
+}
+
+type Key = (KeyCode, KeyModifiers);
+
+pub struct KeyBinding {
+    pub context: Context,
+    pub keys: &'static [Key],
+    pub action: Action,
+    pub label: &'static str,
+    pub description: &'static str,
+    pub footer: bool,
+}
+
+const fn char(c: char) -> Key {
+    (KeyCode::Char(c), KeyModifiers::NONE)
+}
+
+const fn code(code: KeyCode) -> Key {
+    (code, KeyModifiers::NONE)
+}
+
+const fn ctrl(c: char) -> Key {
+    (KeyCode::Char(c), KeyModifiers::CONTROL)
+}
+
+pub const KEYMAP: &[KeyBinding] = &[
+    KeyBinding {
+        context: Context::Global,
+        keys: &[ctrl('c')],
+        action: Action::Exit,
+        label: "quit",
+        description: "Quit rdu",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[code(KeyCode::Esc)],
+        action: Action::ClearSearch,
+        label: "exit",
+        description: "Clear the search, or quit rdu",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('q')],
+        action: Action::Back,
+        label: "back/quit",
+        description: "Go to the parent directory, quit at the top level",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[code(KeyCode::Enter), char('o')],
+        action: Action::Open,
+        label: "open",
+        description: "Open the selected directory",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('j'), code(KeyCode::Down)],
+        action: Action::NextRow,
+        label: "down",
+        description: "Select the next row",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('k'), code(KeyCode::Up)],
+        action: Action::PreviousRow,
+        label: "up",
+        description: "Select the previous row",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('d')],
+        action: Action::Delete,
+        label: "delete",
+        description: "Delete the selected entry permanently",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('t')],
+        action: Action::Trash,
+        label: "trash",
+        description: "Move the selected entry to the trash",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('u')],
+        action: Action::Restore,
+        label: "restore",
+        description: "Restore the last trashed entry",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('r')],
+        action: Action::Rescan,
+        label: "rescan",
+        description: "Rescan the selected directory, or the current one when a file is selected",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('/')],
+        action: Action::Search,
+        label: "search",
+        description: "Search the current directory by name",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('F')],
+        action: Action::Find,
+        label: "find",
+        description: "Find entries by name anywhere in the scanned tree",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('L')],
+        action: Action::LargestFiles,
+        label: "largest",
+        description: "List the largest files anywhere in the scanned tree",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('b')],
+        action: Action::Breakdown,
+        label: "types",
+        description: "Break down the current directory by file extension or category",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('n')],
+        action: Action::NextMatch,
+        label: "next match",
+        description: "Select the next row matching the search",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('N')],
+        action: Action::PreviousMatch,
+        label: "previous match",
+        description: "Select the previous row matching the search",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('s')],
+        action: Action::NextSortKey,
+        label: "sort",
+        description: "Sort by the next column: size, name, items, modification time",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('S')],
+        action: Action::ReverseSort,
+        label: "reverse",
+        description: "Reverse the sort order",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('f')],
+        action: Action::DirsFirst,
+        label: "dirs first",
+        description: "Toggle listing directories before files",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('a')],
+        action: Action::ToggleSizeMetric,
+        label: "apparent size",
+        description: "Toggle between disk usage and apparent size",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('g')],
+        action: Action::NextUsageColumn,
+        label: "graph",
+        description: "Cycle the usage column between percent and bar, both, or hidden",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('G')],
+        action: Action::ToggleUsageBase,
+        label: "graph base",
+        description: "Scale the usage column to the parent or to the largest entry",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('e')],
+        action: Action::Export,
+        label: "export",
+        description: "Export the whole scan as ncdu JSON into the working directory",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('E')],
+        action: Action::ShowErrors,
+        label: "errors",
+        description: "List the paths that could not be fully scanned",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Browser,
+        keys: &[char('h'), char('?')],
+        action: Action::Help,
+        label: "help",
+        description: "Show this help",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Search,
+        keys: &[code(KeyCode::Enter)],
+        action: Action::AcceptSearch,
+        label: "done",
+        description: "Keep the search and return to the table",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Search,
+        keys: &[code(KeyCode::Esc)],
+        action: Action::CancelSearch,
+        label: "cancel",
+        description: "Clear the search",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Search,
+        keys: &[code(KeyCode::Tab)],
+        action: Action::NextSearchMode,
+        label: "mode",
+        description: "Switch between substring, glob and regex patterns",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Search,
+        keys: &[ctrl('f')],
+        action: Action::ToggleSearchFilter,
+        label: "filter",
+        description: "Toggle hiding rows that do not match",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Search,
+        keys: &[code(KeyCode::Backspace)],
+        action: Action::DeleteSearchChar,
+        label: "erase",
+        description: "Delete the last character",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Results,
+        keys: &[char('j'), code(KeyCode::Down)],
+        action: Action::NextResult,
+        label: "down",
+        description: "Select the next result",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Results,
+        keys: &[char('k'), code(KeyCode::Up)],
+        action: Action::PreviousResult,
+        label: "up",
+        description: "Select the previous result",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Results,
+        keys: &[code(KeyCode::Enter), char('o')],
+        action: Action::OpenResult,
+        label: "go to",
+        description: "Open the directory containing the result and select it",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Results,
+        keys: &[code(KeyCode::Esc), char('q')],
+        action: Action::CloseResults,
+        label: "close",
+        description: "Close the result list",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Breakdown,
+        keys: &[char('j'), code(KeyCode::Down)],
+        action: Action::NextGroup,
+        label: "down",
+        description: "Select the next group",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Breakdown,
+        keys: &[char('k'), code(KeyCode::Up)],
+        action: Action::PreviousGroup,
+        label: "up",
+        description: "Select the previous group",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Breakdown,
+        keys: &[code(KeyCode::Enter), char('o')],
+        action: Action::OpenGroup,
+        label: "files",
+        description: "List the largest files in the selected group",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Breakdown,
+        keys: &[code(KeyCode::Tab)],
+        action: Action::NextGrouping,
+        label: "group by",
+        description: "Switch between grouping by extension and by category",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Breakdown,
+        keys: &[code(KeyCode::Esc), char('q')],
+        action: Action::CloseBreakdown,
+        label: "close",
+        description: "Close the breakdown",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Overlay,
+        keys: &[char('j'), code(KeyCode::Down)],
+        action: Action::ScrollDown,
+        label: "down",
+        description: "Scroll down",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Overlay,
+        keys: &[char('k'), code(KeyCode::Up)],
+        action: Action::ScrollUp,
+        label: "up",
+        description: "Scroll up",
+        footer: false,
+    },
+    KeyBinding {
+        context: Context::Overlay,
+        keys: &[code(KeyCode::Esc), char('q'), char('h'), char('?')],
+        action: Action::CloseOverlay,
+        label: "close",
+        description: "Close the help or list",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Confirm,
+        keys: &[char('y'), code(KeyCode::Enter)],
+        action: Action::Confirm,
+        label: "yes",
+        description: "Confirm",
+        footer: true,
+    },
+    KeyBinding {
+        context: Context::Confirm,
+        keys: &[char('n'), code(KeyCode::Esc), char('q')],
+        action: Action::Cancel,
+        label: "no",
+        description: "Cancel",
+        footer: true,
+    },
+];
+
+pub fn action(context: Context, event: KeyEvent) -> Option<Action> {
+    let modifiers = event.modifiers.difference(KeyModifiers::SHIFT);
+    [Context::Global, context].into_iter().find_map(|context| {
+        KEYMAP
+            .iter()
+            .filter(|binding| binding.context == context)
+            .find(|binding| binding.keys.contains(&(event.code, modifiers)))
+            .map(|binding| binding.action)
+    })
+}
+
+pub fn key_label((code, modifiers): &Key) -> String {
+    let key = match code {
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Enter => String::from("Enter"),
+        KeyCode::Esc => String::from("Esc"),
+        KeyCode::Up => String::from("↑"),
+        KeyCode::Down => String::from("↓"),
+        code => code.to_string(),
+    };
+    if modifiers.contains(KeyModifiers::CONTROL) {
+        format!("Ctrl+{key}")
+    } else {
+        key
+    }
+}
+
+pub fn hints(context: Context) -> Vec<String> {
+    KEYMAP
+        .iter()
+        .filter(|binding| binding.context == context && binding.footer)
+        .map(|binding| format!("[{}: {}]", key_label(&binding.keys[0]), binding.label))
+        .collect()
+}
+
+pub fn hint_lines(context: Context, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for hint in hints(context) {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + hint.chars().count() + 3 <= width => {
+                line.push_str(" - ");
+                line.push_str(&hint);
+            }
+            _ => lines.push(hint),
+        }
+    }
+    lines
+}
@@ -0,0 +1,235 @@
+use super::{Exclusion, ExportFormat, ReportMode, SizeMetric, Units, UsageBase, UsageColumn};
+use clap::{CommandFactory, Parser, ValueEnum, error::ErrorKind as ClapErrorKind};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+    thread::available_parallelism,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum IgnoreMode {
+    /// Scan ignored entries and tag them
+    Mark,
+    /// List ignored entries without scanning or counting them
+    Skip,
+}
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Options {
+    /// Directories to scan (defaults to the current directory)
+    #[arg(value_name = "PATH")]
+    pub paths: Vec<PathBuf>,
+
+    /// Number of scanner threads (defaults to twice the available cores)
+    #[arg(short, long, value_name = "N")]
+    pub threads: Option<usize>,
+
+    /// Stay on the filesystem of each scanned path and list mount points without descending (default)
+    #[arg(short = 'x', long, overrides_with = "cross_mounts")]
+    pub one_file_system: bool,
+
+    /// Descend into directories mounted from other filesystems
+    #[arg(long, overrides_with = "one_file_system")]
+    pub cross_mounts: bool,
+
+    /// Follow symbolic links to files and directories
+    #[arg(short = 'L', long)]
+    pub follow_symlinks: bool,
+
+    /// Skip entries whose name or path matches GLOB (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// Read exclude patterns from FILE, one per line
+    #[arg(short = 'X', long, value_name = "FILE")]
+    pub exclude_from: Vec<PathBuf>,
+
+    /// Skip directories containing a valid CACHEDIR.TAG
+    #[arg(long)]
+    pub exclude_caches: bool,
+
+    /// Evaluate .gitignore, .ignore and .git/info/exclude files and mark ignored entries, or skip them
+    #[arg(
+        long,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "mark"
+    )]
+    pub gitignore: Option<IgnoreMode>,
+
+    /// Do not descend into directories deeper than N levels below a scanned path
+    #[arg(short = 'd', long, value_name = "N")]
+    pub max_depth: Option<usize>,
+
+    /// Show and sort by apparent size instead of disk usage
+    #[arg(long)]
+    pub apparent_size: bool,
+
+    /// How to show each entry's share of the directory in the table
+    #[arg(long, value_name = "STYLE", default_value = "both")]
+    pub usage: UsageColumn,
+
+    /// What the usage percentage and bar are relative to
+    #[arg(long, value_name = "BASE", default_value = "parent")]
+    pub usage_relative: UsageBase,
+
+    /// Keep the tree up to date with filesystem changes after the scan (Linux only)
+    #[arg(short = 'w', long, conflicts_with_all = ["import", "export"])]
+    pub watch: bool,
+
+    /// Scan without the interface and write an ncdu-compatible JSON dump to FILE ("-" for stdout)
+    #[arg(short = 'o', long, value_name = "FILE")]
+    pub export: Option<PathBuf>,
+
+    /// Scan without the interface and write a self-contained HTML report to FILE
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["export", "report", "watch"])]
+    pub html: Option<PathBuf>,
+
+    /// Format of the export (defaults to csv or tsv by the FILE extension, ncdu otherwise)
+    #[arg(long, value_name = "FORMAT", requires = "export")]
+    pub export_format: Option<ExportFormat>,
+
+    /// Browse a previously exported JSON dump read-only instead of scanning ("-" for stdin)
+    #[arg(short = 'f', long, value_name = "FILE", conflicts_with = "paths")]
+    pub import: Option<PathBuf>,
+
+    /// Print a report to stdout instead of starting the interface
+    #[arg(
+        long,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "tree",
+        conflicts_with_all = ["export", "watch"]
+    )]
+    pub report: Option<ReportMode>,
+
+    /// Number of entries per directory, or of files in a flat report
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    pub top: usize,
+
+    /// Number of directory levels shown in a report (tree defaults to 1, du to all)
+    #[arg(long, value_name = "N")]
+    pub report_depth: Option<usize>,
+
+    /// Only print the total of each scanned path in a du report, like du -s
+    #[arg(
+        short = 's',
+        long,
+        requires = "report",
+        conflicts_with_all = ["report_depth", "all"]
+    )]
+    pub summarize: bool,
+
+    /// Also list files in a du report, like du -a
+    #[arg(short = 'a', long, requires = "report")]
+    pub all: bool,
+
+    /// Size units of a du report
+    #[arg(long, value_name = "UNITS", default_value = "blocks")]
+    pub units: Units,
+
+    #[arg(skip)]
+    exclude_set: GlobSet,
+
+    #[arg(skip)]
+    exclude_from_set: GlobSet,
+}
+
+impl Options {
+    pub fn load() -> Result<Self> {
+        let mut options = Self::parse();
+        if (options.summarize || options.all) && options.report != Some(ReportMode::Du) {
+            Self::command()
+                .error(
+                    ClapErrorKind::ArgumentConflict,
+                    "--summarize and --all only apply to --report=du",
+                )
+                .exit();
+        }
+
+        if options.paths.is_empty() {
+            options.paths.push(PathBuf::from("."));
+        }
+        options.paths = options
+            .paths
+            .iter()
+            .map(std::path::absolute)
+            .collect::<Result<_>>()?;
+
+        let mut exclude_from = Vec::new();
+        for path in &options.exclude_from {
+            let patterns = fs::read_to_string(path)
+                .map_err(|e| Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+            exclude_from.extend(
+                patterns
+                    .lines()
+                    .map(str::trim_end)
+                    .filter(|line| !line.is_empty())
+                    .map(String::from),
+            );
+        }
+
+        if options.summarize {
+            options.report_depth = Some(0);
+        }
+
+        options.exclude_set = glob_set(&options.exclude)?;
+        options.exclude_from_set = glob_set(&exclude_from)?;
+
+        Ok(options)
+    }
+
+    pub fn stay_on_filesystem(&self) -> bool {
+        self.one_file_system || !self.cross_mounts
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.import.is_some()
+    }
+
+    pub fn size_metric(&self) -> SizeMetric {
+        if self.apparent_size {
+            SizeMetric::Apparent
+        } else {
+            SizeMetric::DiskUsage
+        }
+    }
+
+    pub fn thread_count(&self) -> Result<usize> {
+        match self.threads {
+            Some(threads) if threads > 0 => Ok(threads),
+            _ => Ok(available_parallelism()?.get() * 2),
+        }
+    }
+
+    pub fn exclusion(&self, name: &str, path: &Path) -> Option<Exclusion> {
+        let matches = |set: &GlobSet| set.is_match(name) || set.is_match(path);
+        if matches(&self.exclude_set) {
+            Some(Exclusion::Pattern)
+        } else if matches(&self.exclude_from_set) {
+            Some(Exclusion::ExcludeFrom)
+        } else {
+            None
+        }
+    }
+
+    pub fn descend(&self, depth: usize) -> bool {
+        self.max_depth.is_none_or(|max_depth| depth < max_depth)
+    }
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+}
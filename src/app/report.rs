@@ -0,0 +1,213 @@
+use super::{
+    FileDirectory, Options, SizeMetric, format_bytes,
+    sort::{SortKey, SortOrder},
+};
+use clap::ValueEnum;
+use std::{
+    io::{BufWriter, Result, Write, stdout},
+    sync::Arc,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportMode {
+    /// The largest entries of each directory, indented by depth
+    Tree,
+    /// The largest files anywhere below the scanned paths
+    Flat,
+    /// Directory totals in du's tab-separated format
+    Du,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Units {
+    /// 1024-byte blocks, rounded up
+    Blocks,
+    /// Exact bytes
+    Bytes,
+    /// Powers of 1024 with a suffix, like du -h
+    Human,
+}
+
+impl Units {
+    fn format(self, bytes: u64) -> String {
+        match self {
+            Units::Blocks => bytes.div_ceil(1024).to_string(),
+            Units::Bytes => bytes.to_string(),
+            Units::Human => human_size(bytes),
+        }
+    }
+}
+
+pub fn print_report(root: &Arc<FileDirectory>, options: &Options, errors: usize) -> Result<()> {
+    let mut writer = BufWriter::new(stdout().lock());
+    let metric = options.size_metric();
+
+    match options.report {
+        Some(ReportMode::Flat) => {
+            for file in root.largest_files(metric, options.top) {
+                writeln!(
+                    writer,
+                    "{:>12}  {}",
+                    format_bytes(file.size(metric)),
+                    file.path.display()
+                )?;
+            }
+        }
+        Some(ReportMode::Du) => {
+            if root.path.as_os_str().is_empty() {
+                for entry in root.entries.lock().unwrap().clone() {
+                    write_du(&mut writer, &entry, options, metric, 0)?;
+                }
+            } else {
+                write_du(&mut writer, root, options, metric, 0)?;
+            }
+        }
+        Some(ReportMode::Tree) | None => {
+            let name = if root.path.as_os_str().is_empty() {
+                String::from("total")
+            } else {
+                root.path.display().to_string()
+            };
+            writeln!(writer, "{:>12}  {name}", format_bytes(root.size(metric)))?;
+            write_children(&mut writer, root, options, metric, 1)?;
+        }
+    }
+
+    writer.flush()?;
+    if errors > 0 {
+        eprintln!("rdu: {errors} paths could not be read");
+    }
+    Ok(())
+}
+
+fn write_children<W: Write>(
+    writer: &mut W,
+    directory: &FileDirectory,
+    options: &Options,
+    metric: SizeMetric,
+    depth: usize,
+) -> Result<()> {
+    if depth > options.report_depth.unwrap_or(1) {
+        return Ok(());
+    }
+
+    directory.sort_entries(&SortOrder::new(), metric);
+    let entries = directory.entries.lock().unwrap().clone();
+    let indent = "  ".repeat(depth);
+
+    for entry in entries.iter().take(options.top) {
+        let suffix = if entry.is_dir { "/" } else { "" };
+        writeln!(
+            writer,
+            "{:>12}  {indent}{}{suffix}",
+            format_bytes(entry.size(metric)),
+            entry.name
+        )?;
+        if entry.is_dir {
+            write_children(writer, entry, options, metric, depth + 1)?;
+        }
+    }
+
+    if let Some(rest) = entries.get(options.top..).filter(|rest| !rest.is_empty()) {
+        let size: u64 = rest
+            .iter()
+            .filter(|entry| !entry.is_duplicate_link())
+            .map(|entry| entry.size(metric))
+            .sum();
+        writeln!(
+            writer,
+            "{:>12}  {indent}({} more entries)",
+            format_bytes(size),
+            rest.len()
+        )?;
+    }
+    Ok(())
+}
+
+fn write_du<W: Write>(
+    writer: &mut W,
+    entry: &FileDirectory,
+    options: &Options,
+    metric: SizeMetric,
+    depth: usize,
+) -> Result<()> {
+    if entry.exclusion.is_some() || entry.is_duplicate_link() {
+        return Ok(());
+    }
+    if entry.is_dir {
+        let order = SortOrder {
+            key: SortKey::Name,
+            descending: false,
+            dirs_first: false,
+        };
+        entry.sort_entries(&order, metric);
+        for child in entry.entries.lock().unwrap().clone() {
+            write_du(writer, &child, options, metric, depth + 1)?;
+        }
+    } else if !options.all && depth > 0 {
+        return Ok(());
+    }
+
+    if options
+        .report_depth
+        .is_none_or(|max_depth| depth <= max_depth)
+    {
+        writeln!(
+            writer,
+            "{}\t{}",
+            options.units.format(entry.size(metric)),
+            entry.path.display()
+        )?;
+    }
+    Ok(())
+}
+
+fn human_size(bytes: u64) -> String {
+    const SUFFIXES: [char; 6] = ['K', 'M', 'G', 'T', 'P', 'E'];
+
+    if bytes < 1024 {
+        return bytes.to_string();
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut suffix = 0;
+    while value >= 1024.0 && suffix + 1 < SUFFIXES.len() {
+        value /= 1024.0;
+        suffix += 1;
+    }
+
+    if value < 10.0 {
+        let value = (value * 10.0).ceil() / 10.0;
+        if value < 10.0 {
+            return format!("{value:.1}{}", SUFFIXES[suffix]);
+        }
+    }
+    format!("{}{}", value.ceil(), SUFFIXES[suffix])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn human_size_rounds_up_like_du() {
+        assert_eq!(human_size(0), "0");
+        assert_eq!(human_size(1023), "1023");
+        assert_eq!(human_size(1024), "1.0K");
+        assert_eq!(human_size(1025), "1.1K");
+        assert_eq!(human_size(10 * 1024 - 1), "10K");
+        assert_eq!(human_size(15 * 1024 + 1), "16K");
+        assert_eq!(human_size(1024 * 1024), "1.0M");
+        assert_eq!(human_size(3 << 30), "3.0G");
+        assert_eq!(human_size(u64::MAX), "16E");
+    }
+
+    #[test]
+    fn units_format_blocks_bytes_and_human() {
+        assert_eq!(Units::Blocks.format(0), "0");
+        assert_eq!(Units::Blocks.format(1), "1");
+        assert_eq!(Units::Blocks.format(4096), "4");
+        assert_eq!(Units::Blocks.format(4097), "5");
+        assert_eq!(Units::Bytes.format(4097), "4097");
+        assert_eq!(Units::Human.format(4096), "4.0K");
+    }
+}
@@ -0,0 +1,31 @@
+use std::{
+    fmt,
+    io::{Error, ErrorKind},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanError {
+    PermissionDenied,
+    Vanished,
+    Io(String),
+}
+
+impl From<&Error> for ScanError {
+    fn from(error: &Error) -> Self {
+        match error.kind() {
+            ErrorKind::PermissionDenied => ScanError::PermissionDenied,
+            ErrorKind::NotFound => ScanError::Vanished,
+            _ => ScanError::Io(error.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScanError::PermissionDenied => f.write_str("permission denied"),
+            ScanError::Vanished => f.write_str("vanished during scan"),
+            ScanError::Io(message) => write!(f, "I/O error: {message}"),
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use globset::GlobBuilder;
+use regex::bytes::{Regex, RegexBuilder};
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    Substring,
+    Glob,
+    Regex,
+}
+
+impl SearchMode {
+    pub fn next(self) -> Self {
+        match self {
+            SearchMode::Substring => SearchMode::Glob,
+            SearchMode::Glob => SearchMode::Regex,
+            SearchMode::Regex => SearchMode::Substring,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SearchMode::Substring => "substring",
+            SearchMode::Glob => "glob",
+            SearchMode::Regex => "regex",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Search {
+    pub query: String,
+    pub mode: SearchMode,
+    pub filter: bool,
+    pub global: bool,
+    pub invalid: bool,
+    regex: Option<Regex>,
+}
+
+impl Search {
+    pub fn new(mode: SearchMode, global: bool) -> Self {
+        Self {
+            query: String::new(),
+            mode,
+            filter: true,
+            global,
+            invalid: false,
+            regex: None,
+        }
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.compile();
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.compile();
+    }
+
+    pub fn next_mode(&mut self) {
+        self.mode = self.mode.next();
+        self.compile();
+    }
+
+    pub fn toggle_filter(&mut self) {
+        self.filter = !self.filter;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.regex.is_some()
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        self.regex
+            .as_ref()
+            .is_none_or(|regex| regex.is_match(name.as_bytes()))
+    }
+
+    pub fn match_ranges(&self, name: &str) -> Vec<Range<usize>> {
+        let Some(regex) = &self.regex else {
+            return Vec::new();
+        };
+        let ranges: Vec<_> = regex
+            .find_iter(name.as_bytes())
+            .map(|found| found.range())
+            .filter(|range| !range.is_empty())
+            .collect();
+        if ranges
+            .iter()
+            .all(|range| name.is_char_boundary(range.start) && name.is_char_boundary(range.end))
+        {
+            ranges
+        } else {
+            let whole_name = 0..name.len();
+            vec![whole_name]
+        }
+    }
+
+    fn compile(&mut self) {
+        self.invalid = false;
+        self.regex = None;
+        if self.query.is_empty() {
+            return;
+        }
+
+        let case_insensitive = !self.query.chars().any(char::is_uppercase);
+        let pattern = match self.mode {
+            SearchMode::Substring => Some(regex::escape(&self.query)),
+            SearchMode::Glob => GlobBuilder::new(&self.query)
+                .case_insensitive(case_insensitive)
+                .literal_separator(true)
+                .build()
+                .map(|glob| glob.regex().to_string())
+                .ok(),
+            SearchMode::Regex => Some(self.query.clone()),
+        };
+        self.regex = pattern.and_then(|pattern| {
+            RegexBuilder::new(&pattern)
+                .case_insensitive(case_insensitive && self.mode != SearchMode::Glob)
+                .build()
+                .ok()
+        });
+        self.invalid = self.regex.is_none();
+    }
+}
@@ -0,0 +1,106 @@
+use super::{FileDirectory, file_directory::SizeMetric, utils::natural_cmp};
+use std::{cmp::Ordering, sync::Arc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Size,
+    Name,
+    Items,
+    Modified,
+}
+
+impl SortKey {
+    pub fn next(self) -> Self {
+        match self {
+            SortKey::Size => SortKey::Name,
+            SortKey::Name => SortKey::Items,
+            SortKey::Items => SortKey::Modified,
+            SortKey::Modified => SortKey::Size,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Size => "size",
+            SortKey::Name => "name",
+            SortKey::Items => "items",
+            SortKey::Modified => "mtime",
+        }
+    }
+
+    fn descending_by_default(self) -> bool {
+        self != SortKey::Name
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SortOrder {
+    pub key: SortKey,
+    pub descending: bool,
+    pub dirs_first: bool,
+}
+
+impl SortOrder {
+    pub const fn new() -> Self {
+        Self {
+            key: SortKey::Size,
+            descending: true,
+            dirs_first: false,
+        }
+    }
+
+    pub fn next_key(&mut self) {
+        self.key = self.key.next();
+        self.descending = self.key.descending_by_default();
+    }
+
+    pub fn reverse(&mut self) {
+        self.descending = !self.descending;
+    }
+
+    pub fn toggle_dirs_first(&mut self) {
+        self.dirs_first = !self.dirs_first;
+    }
+
+    pub fn label(&self) -> String {
+        let arrow = if self.descending { "▼" } else { "▲" };
+        if self.dirs_first {
+            format!("{} {arrow}, dirs first", self.key.label())
+        } else {
+            format!("{} {arrow}", self.key.label())
+        }
+    }
+
+    /// Reads the size or item count once per sort, since both keep changing
+    /// while the scan is still running.
+    pub fn key(&self, metric: SizeMetric, entry: &FileDirectory) -> u64 {
+        match self.key {
+            SortKey::Size => entry.size(metric),
+            SortKey::Items => entry.item_count(),
+            SortKey::Name | SortKey::Modified => 0,
+        }
+    }
+
+    pub fn compare(
+        &self,
+        (a_key, a): &(u64, Arc<FileDirectory>),
+        (b_key, b): &(u64, Arc<FileDirectory>),
+    ) -> Ordering {
+        if self.dirs_first && a.is_dir != b.is_dir {
+            return b.is_dir.cmp(&a.is_dir);
+        }
+
+        let ordering = match self.key {
+            SortKey::Size | SortKey::Items => a_key.cmp(b_key),
+            SortKey::Name => natural_cmp(&a.name, &b.name),
+            SortKey::Modified => a.modified.cmp(&b.modified),
+        };
+        let ordering = if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        };
+
+        ordering.then_with(|| natural_cmp(&a.name, &b.name))
+    }
+}
@@ -1,22 +1,86 @@
-use super::FileDirectory;
+use super::{
+    Exclusion, FileDirectory, HiddenTotals, IgnoreMode, IgnoreRules, Options, ScanError, Watcher,
+};
 use std::{
     collections::HashSet,
-    fs,
-    io::Result,
-    path::PathBuf,
+    fs::{self, FileType},
+    io::{ErrorKind, Read, Result},
+    path::{Path, PathBuf},
     sync::{
-        Arc, Mutex, RwLock, Weak,
-        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        Arc, Mutex, OnceLock, Weak,
+        atomic::{AtomicBool, AtomicU32, Ordering},
         mpsc,
     },
     thread,
+    time::Duration,
 };
 
+const CACHEDIR_TAG_SIGNATURE: &[u8] = b"Signature: 8a477f597d28d172789f06886806bc55";
+
 type Job = Box<dyn FnOnce() -> Result<()> + Send + 'static>;
+type InodeKey = (Option<u64>, u64);
+
+#[derive(Clone)]
+pub struct Scope {
+    root_dev: Option<u64>,
+    depth: usize,
+    ignore_rules: Option<Arc<IgnoreRules>>,
+    ignored: bool,
+}
+
+impl Scope {
+    fn root(options: &Options, path: &Path) -> Result<Self> {
+        let ignore_rules = options.gitignore.and_then(|_| IgnoreRules::for_root(path));
+        let ignored = ignore_rules
+            .as_ref()
+            .is_some_and(|rules| rules.is_ignored(path, true));
+        Ok(Self {
+            root_dev: root_dev(path)?,
+            depth: 0,
+            ignore_rules,
+            ignored,
+        })
+    }
+
+    fn for_directory(options: &Options, directory: &FileDirectory) -> Result<Self> {
+        let mut ancestors = Vec::new();
+        let mut parent = directory.parent.lock().unwrap().upgrade();
+        while let Some(ancestor) = parent.filter(|ancestor| !ancestor.path.as_os_str().is_empty()) {
+            parent = ancestor.parent.lock().unwrap().upgrade();
+            ancestors.push(ancestor);
+        }
+
+        let root_path = ancestors.last().map_or(&directory.path, |root| &root.path);
+        let mut scope = Self::root(options, root_path)?;
+        for ancestor in ancestors.iter().rev() {
+            scope.enter(options, &ancestor.path);
+        }
+        scope.depth = ancestors.len();
+        scope.ignored = directory.is_ignored;
+        Ok(scope)
+    }
+
+    fn enter(&mut self, options: &Options, path: &Path) {
+        if options.gitignore.is_some() {
+            self.ignore_rules = IgnoreRules::child(self.ignore_rules.take(), path);
+        }
+    }
+
+    fn child(&self, ignored: bool) -> Self {
+        Self {
+            depth: self.depth + 1,
+            ignored: self.ignored || ignored,
+            ..self.clone()
+        }
+    }
+}
 
 pub struct ThreadPool {
     sender: mpsc::Sender<Job>,
-    inode_map: Arc<Mutex<HashSet<u64>>>,
+    inode_map: Arc<Mutex<HashSet<InodeKey>>>,
+    directory_map: Mutex<HashSet<InodeKey>>,
+    watcher: OnceLock<Arc<Watcher>>,
+    pub errors: Mutex<Vec<(PathBuf, ScanError)>>,
     pub path_in_progress: Arc<Mutex<String>>,
     pub total_files: Arc<AtomicU32>,
     pub active_count: Arc<AtomicU32>,
@@ -37,11 +101,7 @@ impl ThreadPool {
                     let job = receiver.lock().unwrap().recv();
                     match job {
                         Ok(job) => {
-                            active_count.fetch_add(1, Ordering::Relaxed);
-                            match job() {
-                                Ok(ok) => ok,
-                                Err(_) => {}
-                            }
+                            let _ = job();
                             active_count.fetch_sub(1, Ordering::Relaxed);
                         }
                         Err(_) => break,
@@ -53,15 +113,61 @@ impl ThreadPool {
         Arc::new(Self {
             active_count,
             inode_map: Arc::new(Mutex::new(HashSet::new())),
+            directory_map: Mutex::new(HashSet::new()),
+            watcher: OnceLock::new(),
+            errors: Mutex::new(Vec::new()),
             total_files: Arc::new(AtomicU32::new(0)),
             path_in_progress: Arc::new(Mutex::new(String::from(""))),
             sender: tx,
         })
     }
 
+    pub fn watch(self: &Arc<Self>, options: &Arc<Options>) -> Result<()> {
+        let watcher = Watcher::start(Arc::clone(self), Arc::clone(options))?;
+        let _ = self.watcher.set(watcher);
+        Ok(())
+    }
+
+    pub fn watcher(&self) -> Option<&Arc<Watcher>> {
+        self.watcher.get()
+    }
+
+    pub fn scan(self: Arc<Self>, options: Arc<Options>) -> Result<Arc<FileDirectory>> {
+        if let [path] = options.paths.as_slice() {
+            let name = path.to_string_lossy().into_owned();
+            return self.scan_dir(
+                Arc::clone(&options),
+                Scope::root(&options, path)?,
+                name,
+                path.clone(),
+                Mutex::new(Weak::new()),
+            );
+        }
+
+        let root = Arc::new(FileDirectory {
+            hardlink_count: 1,
+            is_dir: true,
+            ..Default::default()
+        });
+
+        for path in &options.paths {
+            let name = path.to_string_lossy().into_owned();
+            let directory = Arc::clone(&self).scan_dir(
+                Arc::clone(&options),
+                Scope::root(&options, path)?,
+                name,
+                path.clone(),
+                Mutex::new(Arc::downgrade(&root)),
+            )?;
+            Arc::clone(&root).add_entry(directory)?;
+        }
+        Ok(root)
+    }
+
     pub fn scan_dir(
         self: Arc<Self>,
-        root_dev: Option<u64>,
+        options: Arc<Options>,
+        scope: Scope,
         name: String,
         path: PathBuf,
         parent: Mutex<Weak<FileDirectory>>,
@@ -73,105 +179,604 @@ impl ThreadPool {
 
         let metadata = fs::metadata(&path)?;
 
-        let mut blocks: Option<u64> = None;
-        let mut nlink = 1;
-
         #[cfg(unix)]
-        {
+        let (blocks, dev, nlink, inode, owner) = {
             use std::os::unix::fs::MetadataExt;
-            blocks = Some(metadata.blocks());
-            nlink = metadata.nlink();
-        }
+            (
+                Some(metadata.blocks()),
+                Some(metadata.dev()),
+                metadata.nlink(),
+                Some(metadata.ino()),
+                Some(metadata.uid()),
+            )
+        };
+        #[cfg(not(unix))]
+        let (blocks, dev, nlink, inode, owner) = (None, None, 1, None, None);
 
         let directory = Arc::new(FileDirectory {
-            actual_size_bytes: AtomicU64::new(0),
+            apparent_size: metadata.len(),
             blocks,
-            is_hardlink: false,
-            dirty: AtomicBool::new(false),
-            is_symlink: false,
-            entries: Mutex::new(Vec::new()),
+            is_ignored: scope.ignored,
+            truncated: !options.descend(scope.depth),
             hardlink_count: nlink,
+            dev,
+            inode,
+            modified: metadata.modified().ok(),
+            owner,
             is_dir: true,
             name,
             parent,
             path,
+            ..Default::default()
         });
 
-        let directory_clone = Arc::clone(&directory);
+        if directory.truncated {
+            self.count_hidden_in_background(options, scope, Arc::clone(&directory));
+            return Ok(directory);
+        }
 
-        Arc::clone(&self).execute(move || {
-            let directory = Arc::clone(&directory_clone);
-            let inode_map = Arc::clone(&self.inode_map);
+        if options.follow_symlinks
+            && let Some(inode) = inode
+            && !self.directory_map.lock().unwrap().insert((dev, inode))
+        {
+            return Ok(directory);
+        }
 
-            for entry in fs::read_dir(&directory.path)? {
-                let entry = entry?;
-                let metadata = entry.metadata()?;
-                let name = entry.file_name().to_string_lossy().into_owned();
-                let path = entry.path();
+        self.read_entries_in_background(options, scope, Arc::clone(&directory));
+        Ok(directory)
+    }
 
-                let mut blocks: Option<u64> = None;
-                let mut dev: Option<u64> = None;
-                let mut nlink = 1;
-                let mut inode: Option<u64> = None;
+    pub fn rescan(
+        self: Arc<Self>,
+        options: Arc<Options>,
+        directory: &Arc<FileDirectory>,
+    ) -> Result<()> {
+        let scope = Scope::for_directory(&options, directory)?;
+        self.errors
+            .lock()
+            .unwrap()
+            .retain(|(path, _)| !path.starts_with(&directory.path));
+        if !options.descend(scope.depth) {
+            let hidden = directory.set_hidden(HiddenTotals::default());
+            if self.forget_hidden(&hidden) {
+                self.promote_duplicate_links(directory);
+            }
+            self.count_hidden_in_background(options, scope, Arc::clone(directory));
+            return Ok(());
+        }
 
-                #[cfg(unix)]
+        let mut released = false;
+        for entry in directory.take_entries() {
+            released |= self.forget(&entry);
+        }
+        if released {
+            self.promote_duplicate_links(directory);
+        }
+
+        self.read_entries_in_background(options, scope, Arc::clone(directory));
+        Ok(())
+    }
+
+    pub fn remove_entry(&self, directory: &Arc<FileDirectory>, name: &str) {
+        let entry = directory
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|entry| entry.name == name)
+            .cloned();
+        if let Some(entry) = entry {
+            self.detach_entry(&entry);
+        }
+    }
+
+    pub fn detach_entry(&self, entry: &Arc<FileDirectory>) {
+        self.errors
+            .lock()
+            .unwrap()
+            .retain(|(path, _)| !path.starts_with(&entry.path));
+        let parent = entry.parent.lock().unwrap().upgrade();
+        entry.detach();
+        if self.forget(entry)
+            && let Some(parent) = parent
+        {
+            self.promote_duplicate_links(&parent);
+        }
+    }
+
+    pub fn attach_entry(&self, directory: &Arc<FileDirectory>, entry: &Arc<FileDirectory>) {
+        self.remove_entry(directory, &entry.name);
+        *entry.parent.lock().unwrap() = Arc::downgrade(directory);
+        self.remember(entry);
+        entry.attach();
+    }
+
+    pub fn refresh_entry(
+        self: &Arc<Self>,
+        options: &Arc<Options>,
+        directory: &Arc<FileDirectory>,
+        name: String,
+    ) -> Result<()> {
+        self.remove_entry(directory, &name);
+
+        let path = directory.path.join(&name);
+        let file_type = fs::symlink_metadata(&path).map(|metadata| metadata.file_type());
+        if file_type
+            .as_ref()
+            .is_err_and(|e| e.kind() == ErrorKind::NotFound)
+        {
+            return Ok(());
+        }
+
+        let mut scope = Scope::for_directory(options, directory)?;
+        scope.enter(options, &directory.path);
+        self.read_entry(options, &scope, directory, name, path, file_type)
+    }
+
+    /// Returns whether a counted hardlink was released, so that one of its
+    /// duplicates may now have to be counted instead.
+    fn forget(&self, entry: &FileDirectory) -> bool {
+        self.account(entry, false)
+    }
+
+    fn remember(&self, entry: &FileDirectory) {
+        self.account(entry, true);
+    }
+
+    fn account(&self, entry: &FileDirectory, attached: bool) -> bool {
+        let mut released = false;
+        if let Some(inode) = entry.inode {
+            let key = (entry.dev, inode);
+            let map = if entry.is_dir {
+                Some(&self.directory_map)
+            } else if entry.is_hardlink && !entry.is_duplicate_link() {
+                Some(&*self.inode_map)
+            } else {
+                None
+            };
+            if let Some(map) = map {
+                if attached {
+                    map.lock().unwrap().insert(key);
+                } else {
+                    released |= map.lock().unwrap().remove(&key) && !entry.is_dir;
+                }
+            }
+        }
+        let hidden = entry.hidden.lock().unwrap().clone();
+        if attached {
+            self.remember_hidden(&hidden);
+        } else {
+            released |= self.forget_hidden(&hidden);
+        }
+        let mut files = 0;
+        if !entry.is_dir && entry.exclusion.is_none() && entry.error.lock().unwrap().is_none() {
+            files += 1;
+        }
+        if attached {
+            self.total_files.fetch_add(files, Ordering::Relaxed);
+        } else {
+            self.total_files.fetch_sub(files, Ordering::Relaxed);
+        }
+        for child in entry.entries.lock().unwrap().iter() {
+            released |= self.account(child, attached);
+        }
+        released
+    }
+
+    /// Counts the first duplicate of every hardlink that no longer has a
+    /// counted link in the tree containing `anchor`.
+    fn promote_duplicate_links(&self, anchor: &Arc<FileDirectory>) {
+        let mut root = Arc::clone(anchor);
+        loop {
+            let parent = root.parent.lock().unwrap().upgrade();
+            match parent {
+                Some(parent) => root = parent,
+                None => break,
+            }
+        }
+
+        let mut pending = vec![root];
+        while let Some(directory) = pending.pop() {
+            for entry in directory.entries.lock().unwrap().iter() {
+                if entry.is_dir {
+                    pending.push(Arc::clone(entry));
+                } else if entry.is_duplicate_link()
+                    && let Some(inode) = entry.inode
+                    && self.inode_map.lock().unwrap().insert((entry.dev, inode))
                 {
-                    use std::os::unix::fs::MetadataExt;
-                    blocks = Some(metadata.blocks());
-                    dev = Some(metadata.dev());
-                    inode = Some(metadata.ino());
-                    nlink = metadata.nlink();
+                    entry.set_duplicate_link(false);
                 }
+            }
+        }
+    }
+
+    fn count_hidden_in_background(
+        self: Arc<Self>,
+        options: Arc<Options>,
+        scope: Scope,
+        directory: Arc<FileDirectory>,
+    ) {
+        Arc::clone(&self).execute(move || {
+            let totals = self.count_hidden(&options, scope, &directory.path);
+            self.total_files
+                .fetch_add(totals.files as u32, Ordering::Relaxed);
+            directory.set_hidden(totals);
+            Ok(())
+        });
+    }
 
-                if dev != root_dev {
+    fn forget_hidden(&self, hidden: &HiddenTotals) -> bool {
+        self.total_files
+            .fetch_sub(hidden.files as u32, Ordering::Relaxed);
+        let mut inode_map = self.inode_map.lock().unwrap();
+        for link in &hidden.links {
+            inode_map.remove(link);
+        }
+        !hidden.links.is_empty()
+    }
+
+    fn remember_hidden(&self, hidden: &HiddenTotals) {
+        self.total_files
+            .fetch_add(hidden.files as u32, Ordering::Relaxed);
+        self.inode_map
+            .lock()
+            .unwrap()
+            .extend(hidden.links.iter().copied());
+    }
+
+    fn count_hidden(&self, options: &Options, scope: Scope, path: &Path) -> HiddenTotals {
+        let mut totals = HiddenTotals::default();
+        let mut pending = vec![(path.to_path_buf(), scope)];
+
+        while let Some((directory, mut scope)) = pending.pop() {
+            scope.enter(options, &directory);
+            let entries = match fs::read_dir(&directory) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    self.record_hidden_error(&mut totals, directory, &e);
                     continue;
                 }
-
-                if let Some(inode) = inode {
-                    let mut inode_map = inode_map.lock().unwrap();
-                    if !inode_map.contains(&inode) {
-                        inode_map.insert(inode);
-                    } else {
+            };
+            for entry in entries {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        self.record_hidden_error(&mut totals, directory.clone(), &e);
+                        continue;
+                    }
+                };
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let path = entry.path();
+                let file_type = match entry.file_type() {
+                    Ok(file_type) => file_type,
+                    Err(e) => {
+                        self.record_hidden_error(&mut totals, path, &e);
                         continue;
                     }
+                };
+                if options.exclusion(&name, &path).is_some() {
+                    continue;
+                }
+                let is_ignored = scope
+                    .ignore_rules
+                    .as_ref()
+                    .is_some_and(|rules| rules.is_ignored(&path, file_type.is_dir()));
+                if is_ignored && options.gitignore == Some(IgnoreMode::Skip) {
+                    continue;
+                }
+                let metadata = if file_type.is_symlink() && options.follow_symlinks {
+                    fs::metadata(&path).or_else(|_| fs::symlink_metadata(&path))
+                } else {
+                    fs::symlink_metadata(&path)
+                };
+                let metadata = match metadata {
+                    Ok(metadata) => metadata,
+                    Err(e) => {
+                        self.record_hidden_error(&mut totals, path, &e);
+                        continue;
+                    }
+                };
+                if metadata.is_dir() && options.exclude_caches && is_cache_dir(&path) {
+                    continue;
                 }
 
-                if metadata.is_file() | metadata.is_symlink() {
-                    let file = Arc::new(FileDirectory {
-                        actual_size_bytes: AtomicU64::new(0),
-                        blocks,
-                        hardlink_count: nlink,
-                        is_hardlink: if nlink > 1 { true } else { false },
-                        is_symlink: metadata.is_symlink(),
-                        dirty: AtomicBool::new(false),
-                        entries: Mutex::new(Vec::new()),
-                        is_dir: false,
-                        name,
-                        parent: Mutex::new(Arc::downgrade(&directory)),
-                        path,
-                    });
-
-                    Arc::clone(&directory).add_entry(Arc::clone(&file))?;
-                    Arc::clone(&self.total_files).fetch_add(1, Ordering::Relaxed);
-                } else if metadata.is_dir() {
-                    let entry_dir = Arc::clone(&self).scan_dir(
-                        root_dev,
-                        name,
-                        path,
-                        Mutex::new(Arc::downgrade(&directory)),
-                    )?;
-                    Arc::clone(&directory).add_entry(Arc::clone(&entry_dir))?;
+                #[cfg(unix)]
+                let (size, dev, nlink, inode) = {
+                    use std::os::unix::fs::MetadataExt;
+                    (
+                        metadata.blocks() * 512,
+                        Some(metadata.dev()),
+                        metadata.nlink(),
+                        Some(metadata.ino()),
+                    )
+                };
+                #[cfg(not(unix))]
+                let (size, dev, nlink, inode) = (metadata.len(), None, 1, None);
+
+                totals.items += 1;
+                if metadata.is_dir() {
+                    let other_filesystem = options.stay_on_filesystem() && dev != scope.root_dev;
+                    if !other_filesystem && !file_type.is_symlink() {
+                        pending.push((path, scope.child(is_ignored)));
+                    }
+                } else {
+                    totals.files += 1;
+                    if let Some(inode) = inode
+                        && nlink > 1
+                    {
+                        if !self.inode_map.lock().unwrap().insert((dev, inode)) {
+                            continue;
+                        }
+                        totals.links.push((dev, inode));
+                    }
                 }
+                totals.size += size;
+                totals.apparent += metadata.len();
+            }
+        }
+        totals
+    }
+
+    fn record_hidden_error(
+        &self,
+        totals: &mut HiddenTotals,
+        path: PathBuf,
+        error: &std::io::Error,
+    ) {
+        totals.errors += 1;
+        self.errors
+            .lock()
+            .unwrap()
+            .push((path, ScanError::from(error)));
+    }
+
+    fn read_entries_in_background(
+        self: Arc<Self>,
+        options: Arc<Options>,
+        scope: Scope,
+        directory: Arc<FileDirectory>,
+    ) {
+        Arc::clone(&self).execute(move || {
+            if let Err(e) = Arc::clone(&self).read_entries(options, scope, &directory) {
+                self.record_error(&directory, &e);
             }
             Ok(())
         });
-        Ok(directory)
+    }
+
+    fn read_entries(
+        self: Arc<Self>,
+        options: Arc<Options>,
+        mut scope: Scope,
+        directory: &Arc<FileDirectory>,
+    ) -> Result<()> {
+        if let Some(watcher) = self.watcher.get() {
+            watcher.add(directory);
+        }
+        scope.enter(&options, &directory.path);
+
+        for entry in fs::read_dir(&directory.path)? {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    self.record_error(directory, &e);
+                    continue;
+                }
+            };
+            let name = entry.file_name().to_string_lossy().into_owned();
+            self.read_entry(
+                &options,
+                &scope,
+                directory,
+                name,
+                entry.path(),
+                entry.file_type(),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn read_entry(
+        self: &Arc<Self>,
+        options: &Arc<Options>,
+        scope: &Scope,
+        directory: &Arc<FileDirectory>,
+        name: String,
+        path: PathBuf,
+        file_type: Result<FileType>,
+    ) -> Result<()> {
+        let is_dir = file_type.as_ref().is_ok_and(|file_type| file_type.is_dir());
+        if let Some(exclusion) = options.exclusion(&name, &path) {
+            self.add_excluded_entry(directory, name, path, is_dir, exclusion)?;
+            return Ok(());
+        }
+
+        let is_ignored = scope
+            .ignore_rules
+            .as_ref()
+            .is_some_and(|rules| rules.is_ignored(&path, is_dir));
+        if is_ignored && options.gitignore == Some(IgnoreMode::Skip) {
+            self.add_excluded_entry(directory, name, path, is_dir, Exclusion::Ignored)?;
+            return Ok(());
+        }
+        let is_ignored = scope.ignored || is_ignored;
+
+        let result = file_type.and_then(|file_type| {
+            let is_symlink = file_type.is_symlink();
+            let metadata = if is_symlink && options.follow_symlinks {
+                fs::metadata(&path).or_else(|_| fs::symlink_metadata(&path))?
+            } else {
+                fs::symlink_metadata(&path)?
+            };
+            Ok((is_symlink, metadata))
+        });
+        let (is_symlink, metadata) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                self.add_error_entry(directory, name, path, is_dir, &e)?;
+                return Ok(());
+            }
+        };
+
+        #[cfg(unix)]
+        let (blocks, dev, nlink, inode, owner) = {
+            use std::os::unix::fs::MetadataExt;
+            (
+                Some(metadata.blocks()),
+                Some(metadata.dev()),
+                metadata.nlink(),
+                Some(metadata.ino()),
+                Some(metadata.uid()),
+            )
+        };
+        #[cfg(not(unix))]
+        let (blocks, dev, nlink, inode, owner) = (None, None, 1, None, None);
+
+        if options.stay_on_filesystem() && dev != scope.root_dev {
+            let mount = Arc::new(FileDirectory {
+                apparent_size: metadata.len(),
+                blocks,
+                hardlink_count: nlink,
+                dev,
+                inode,
+                modified: metadata.modified().ok(),
+                owner,
+                is_dir: metadata.is_dir(),
+                is_ignored,
+                exclusion: Some(Exclusion::OtherFilesystem),
+                name,
+                parent: Mutex::new(Arc::downgrade(directory)),
+                path,
+                ..Default::default()
+            });
+            Arc::clone(directory).add_entry(mount)?;
+            return Ok(());
+        }
+
+        let mut is_duplicate_link = false;
+        if let Some(inode) = inode
+            && !metadata.is_dir()
+            && nlink > 1
+        {
+            is_duplicate_link = !self.inode_map.lock().unwrap().insert((dev, inode));
+        }
+
+        if metadata.is_file() | metadata.is_symlink() {
+            let file = Arc::new(FileDirectory {
+                apparent_size: metadata.len(),
+                blocks,
+                hardlink_count: nlink,
+                dev,
+                inode,
+                modified: metadata.modified().ok(),
+                owner,
+                is_hardlink: nlink > 1,
+                is_duplicate_link: AtomicBool::new(is_duplicate_link),
+                is_ignored,
+                is_symlink,
+                name,
+                parent: Mutex::new(Arc::downgrade(directory)),
+                path,
+                ..Default::default()
+            });
+
+            Arc::clone(directory).add_entry(Arc::clone(&file))?;
+            Arc::clone(&self.total_files).fetch_add(1, Ordering::Relaxed);
+        } else if metadata.is_dir() {
+            if options.exclude_caches && is_cache_dir(&path) {
+                self.add_excluded_entry(directory, name, path, true, Exclusion::Pattern)?;
+                return Ok(());
+            }
+            match Arc::clone(self).scan_dir(
+                Arc::clone(options),
+                scope.child(is_ignored),
+                name.clone(),
+                path.clone(),
+                Mutex::new(Arc::downgrade(directory)),
+            ) {
+                Ok(entry_dir) => Arc::clone(directory).add_entry(entry_dir)?,
+                Err(e) => self.add_error_entry(directory, name, path, metadata.is_dir(), &e)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn add_error_entry(
+        &self,
+        directory: &Arc<FileDirectory>,
+        name: String,
+        path: PathBuf,
+        is_dir: bool,
+        error: &std::io::Error,
+    ) -> Result<()> {
+        let entry = Arc::new(FileDirectory {
+            name,
+            path,
+            is_dir,
+            parent: Mutex::new(Arc::downgrade(directory)),
+            ..Default::default()
+        });
+        self.record_error(&entry, error);
+        Arc::clone(directory).add_entry(entry)
+    }
+
+    fn add_excluded_entry(
+        &self,
+        directory: &Arc<FileDirectory>,
+        name: String,
+        path: PathBuf,
+        is_dir: bool,
+        exclusion: Exclusion,
+    ) -> Result<()> {
+        let entry = Arc::new(FileDirectory {
+            name,
+            path,
+            is_dir,
+            exclusion: Some(exclusion),
+            parent: Mutex::new(Arc::downgrade(directory)),
+            ..Default::default()
+        });
+        Arc::clone(directory).add_entry(entry)
+    }
+
+    pub fn record_error(&self, entry: &FileDirectory, error: &std::io::Error) {
+        let error = ScanError::from(error);
+        entry.set_error(error.clone());
+        self.errors
+            .lock()
+            .unwrap()
+            .push((entry.path.clone(), error));
     }
 
     pub fn execute<F>(&self, f: F)
     where
         F: FnOnce() -> Result<()> + Send + 'static,
     {
+        self.active_count.fetch_add(1, Ordering::Relaxed);
         self.sender.send(Box::new(f)).unwrap();
     }
+
+    pub fn wait(&self) {
+        while self.active_count.load(Ordering::Relaxed) > 0 {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+fn is_cache_dir(path: &Path) -> bool {
+    let mut signature = [0; CACHEDIR_TAG_SIGNATURE.len()];
+    fs::File::open(path.join("CACHEDIR.TAG"))
+        .and_then(|mut file| file.read_exact(&mut signature))
+        .is_ok_and(|_| signature == CACHEDIR_TAG_SIGNATURE)
+}
+
+#[cfg(unix)]
+fn root_dev(path: &Path) -> Result<Option<u64>> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(Some(fs::metadata(path)?.dev()))
+}
+
+#[cfg(not(unix))]
+fn root_dev(_path: &Path) -> Result<Option<u64>> {
+    Ok(None)
 }
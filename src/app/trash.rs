@@ -0,0 +1,166 @@
+use super::utils::format_local_time;
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{Error, ErrorKind, Result, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+#[derive(Debug)]
+pub struct TrashedEntry {
+    pub original_path: PathBuf,
+    pub files_path: PathBuf,
+    pub info_path: PathBuf,
+}
+
+#[cfg(unix)]
+pub fn trash(path: &Path) -> Result<TrashedEntry> {
+    use std::os::unix::fs::MetadataExt;
+
+    let dev = fs::symlink_metadata(path)?.dev();
+    let home_trash = home_trash_dir()?;
+
+    if existing_ancestor_dev(&home_trash)? == dev {
+        move_to_trash(path, &home_trash, None)
+    } else {
+        let topdir = mount_topdir(path, dev)?;
+        // SAFETY: getuid has no preconditions and cannot fail
+        let uid = unsafe { libc::getuid() };
+        let trash_dir = topdir.join(format!(".Trash-{uid}"));
+        move_to_trash(path, &trash_dir, Some(&topdir))
+    }
+}
+
+#[cfg(not(unix))]
+pub fn trash(_path: &Path) -> Result<TrashedEntry> {
+    Err(Error::new(
+        ErrorKind::Unsupported,
+        "trash is only supported on unix",
+    ))
+}
+
+pub fn restore(entry: &TrashedEntry) -> Result<()> {
+    if fs::symlink_metadata(&entry.original_path).is_ok() {
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            format!("{} already exists", entry.original_path.display()),
+        ));
+    }
+    fs::rename(&entry.files_path, &entry.original_path)?;
+    fs::remove_file(&entry.info_path)
+}
+
+fn home_trash_dir() -> Result<PathBuf> {
+    let data_home = match env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => env::home_dir()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "home directory not found"))?
+            .join(".local/share"),
+    };
+    Ok(data_home.join("Trash"))
+}
+
+#[cfg(unix)]
+fn existing_ancestor_dev(path: &Path) -> Result<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    for ancestor in path.ancestors() {
+        if let Ok(metadata) = fs::metadata(ancestor) {
+            return Ok(metadata.dev());
+        }
+    }
+    Err(Error::new(ErrorKind::NotFound, "trash directory not found"))
+}
+
+#[cfg(unix)]
+fn mount_topdir(path: &Path, dev: u64) -> Result<PathBuf> {
+    use std::os::unix::fs::MetadataExt;
+
+    let mut topdir = path.parent().unwrap_or(path).to_path_buf();
+    while let Some(parent) = topdir.parent() {
+        if fs::metadata(parent)?.dev() != dev {
+            break;
+        }
+        topdir = parent.to_path_buf();
+    }
+    Ok(topdir)
+}
+
+#[cfg(unix)]
+fn move_to_trash(path: &Path, trash_dir: &Path, topdir: Option<&Path>) -> Result<TrashedEntry> {
+    use std::os::unix::{ffi::OsStrExt, fs::DirBuilderExt};
+
+    let files_dir = trash_dir.join("files");
+    let info_dir = trash_dir.join("info");
+    for dir in [trash_dir, &files_dir, &info_dir] {
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)?;
+    }
+
+    let name = path
+        .file_name()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "cannot trash a root directory"))?;
+    let recorded_path = match topdir {
+        Some(topdir) => path.strip_prefix(topdir).unwrap_or(path),
+        None => path,
+    };
+    let info = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        percent_encode(recorded_path.as_os_str().as_bytes()),
+        format_local_time(SystemTime::now())
+    );
+
+    for n in 1.. {
+        let mut trash_name = name.to_os_string();
+        if n > 1 {
+            trash_name.push(format!(".{n}"));
+        }
+        let files_path = files_dir.join(&trash_name);
+        trash_name.push(".trashinfo");
+        let info_path = info_dir.join(trash_name);
+
+        let mut info_file = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        };
+        if fs::symlink_metadata(&files_path).is_ok() {
+            fs::remove_file(&info_path)?;
+            continue;
+        }
+
+        let result = info_file
+            .write_all(info.as_bytes())
+            .and_then(|_| fs::rename(path, &files_path));
+        if let Err(e) = result {
+            let _ = fs::remove_file(&info_path);
+            return Err(e);
+        }
+
+        return Ok(TrashedEntry {
+            original_path: path.to_path_buf(),
+            files_path,
+            info_path,
+        });
+    }
+    unreachable!()
+}
+
+fn percent_encode(path: &[u8]) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for &byte in path {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
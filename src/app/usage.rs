@@ -0,0 +1,115 @@
+use clap::ValueEnum;
+
+const BAR_WIDTH: usize = 10;
+// "| 100.0%"
+const PERCENT_WIDTH: u16 = 8;
+const PARTIAL_BLOCKS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum UsageColumn {
+    /// Percentage only
+    Percent,
+    /// Proportional bar only
+    Bar,
+    /// Percentage followed by a bar
+    Both,
+    /// Hide the column
+    Off,
+}
+
+impl UsageColumn {
+    pub fn next(self) -> Self {
+        match self {
+            UsageColumn::Both => UsageColumn::Percent,
+            UsageColumn::Percent => UsageColumn::Bar,
+            UsageColumn::Bar => UsageColumn::Off,
+            UsageColumn::Off => UsageColumn::Both,
+        }
+    }
+
+    pub fn width(self) -> u16 {
+        match self {
+            UsageColumn::Percent => PERCENT_WIDTH,
+            UsageColumn::Bar => BAR_WIDTH as u16 + 2,
+            UsageColumn::Both => PERCENT_WIDTH + 1 + BAR_WIDTH as u16,
+            UsageColumn::Off => 0,
+        }
+    }
+
+    pub fn format(self, size: u64, base: u64) -> String {
+        let ratio = if base > 0 {
+            (size as f64 / base as f64).min(1.0)
+        } else {
+            0.0
+        };
+        match self {
+            UsageColumn::Percent => format!("| {:>5.1}%", ratio * 100.0),
+            UsageColumn::Bar => format!("| {}", bar(ratio)),
+            UsageColumn::Both => format!("| {:>5.1}% {}", ratio * 100.0, bar(ratio)),
+            UsageColumn::Off => String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum UsageBase {
+    /// Relative to the size of the parent directory
+    Parent,
+    /// Relative to the largest entry in the directory
+    Largest,
+}
+
+impl UsageBase {
+    pub fn toggle(self) -> Self {
+        match self {
+            UsageBase::Parent => UsageBase::Largest,
+            UsageBase::Largest => UsageBase::Parent,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            UsageBase::Parent => "of parent",
+            UsageBase::Largest => "of largest",
+        }
+    }
+}
+
+fn bar(ratio: f64) -> String {
+    let eighths = (ratio * (BAR_WIDTH * 8) as f64).round() as usize;
+    let mut bar = "█".repeat(eighths / 8);
+    let partial = eighths % 8;
+    if partial > 0 {
+        bar.push(PARTIAL_BLOCKS[partial - 1]);
+    }
+    let padding = BAR_WIDTH - bar.chars().count();
+    bar + &" ".repeat(padding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bar_rounds_to_the_nearest_eighth() {
+        assert_eq!(bar(0.0), " ".repeat(BAR_WIDTH));
+        assert_eq!(bar(1.0), "█".repeat(BAR_WIDTH));
+        assert_eq!(bar(0.5), format!("{}{}", "█".repeat(5), " ".repeat(5)));
+        assert_eq!(bar(0.0125), format!("▏{}", " ".repeat(9)));
+        assert_eq!(bar(0.006_3), format!("▏{}", " ".repeat(9)));
+        assert_eq!(bar(0.006_2), " ".repeat(BAR_WIDTH));
+        assert_eq!(bar(0.99), format!("{}▉", "█".repeat(9)));
+    }
+
+    #[test]
+    fn format_fills_the_column_width() {
+        for column in [UsageColumn::Percent, UsageColumn::Bar, UsageColumn::Both] {
+            for (size, base) in [(0, 0), (1, 3), (5, 5), (10, 5)] {
+                let text = column.format(size, base);
+                assert_eq!(text.chars().count(), usize::from(column.width()));
+            }
+        }
+        assert_eq!(UsageColumn::Percent.format(1, 4), "|  25.0%");
+        assert_eq!(UsageColumn::Off.format(1, 4), "");
+    }
+}
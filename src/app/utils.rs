@@ -1,3 +1,5 @@
+use std::{cmp::Ordering, iter::Peekable, str::Chars, time::SystemTime};
+
 pub fn format_bytes(bytes: u64) -> String {
     const KB: f64 = 1024.0;
     const MB: f64 = KB * 1024.0;
@@ -18,3 +20,172 @@ pub fn format_bytes(bytes: u64) -> String {
         format!("{:.2} TiB", b / TB)
     }
 }
+
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    compare_natural(a, b, false).then_with(|| compare_natural(a, b, true))
+}
+
+fn compare_natural(a: &str, b: &str, exact: bool) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_digits(&mut a);
+                let y = take_digits(&mut b);
+                let (x_value, y_value) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let mut ordering = x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value));
+                if exact {
+                    ordering = ordering.then_with(|| x.len().cmp(&y.len()));
+                }
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = if exact {
+                    x.cmp(&y)
+                } else {
+                    x.to_lowercase().cmp(y.to_lowercase())
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn take_digits(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        digits.push(c);
+    }
+    digits
+}
+
+#[cfg(unix)]
+pub fn format_local_time(time: SystemTime) -> String {
+    use std::time::UNIX_EPOCH;
+
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as libc::time_t,
+        Err(e) => -(e.duration().as_secs() as libc::time_t),
+    };
+
+    // SAFETY: `tm` is plain old data and localtime_r only writes into it
+    let tm = unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&seconds, &mut tm);
+        tm
+    };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec
+    )
+}
+
+#[cfg(not(unix))]
+pub fn format_local_time(time: SystemTime) -> String {
+    use std::time::UNIX_EPOCH;
+
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(unix)]
+pub fn user_name(uid: u32) -> String {
+    let mut buffer = vec![0; 1024];
+    loop {
+        // SAFETY: `passwd` is plain old data, getpwuid_r only writes into it and
+        // `buffer`, and the name is copied out while `buffer` is still alive
+        let (result, name) = unsafe {
+            let mut passwd: libc::passwd = std::mem::zeroed();
+            let mut found = std::ptr::null_mut();
+            let result = libc::getpwuid_r(
+                uid,
+                &mut passwd,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut found,
+            );
+            let name = (!found.is_null()).then(|| {
+                std::ffi::CStr::from_ptr(passwd.pw_name)
+                    .to_string_lossy()
+                    .into_owned()
+            });
+            (result, name)
+        };
+        if result == libc::ERANGE && buffer.len() < 1 << 20 {
+            buffer.resize(buffer.len() * 2, 0);
+        } else {
+            return name.unwrap_or_else(|| uid.to_string());
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn user_name(uid: u32) -> String {
+    uid.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_cmp_ignores_case_before_breaking_ties() {
+        assert_eq!(natural_cmp("apple", "Apricot"), Ordering::Less);
+        assert_eq!(natural_cmp("Apricot", "apple"), Ordering::Greater);
+        assert_eq!(natural_cmp("Banana", "apple"), Ordering::Greater);
+        assert_eq!(natural_cmp("Apple", "apple"), Ordering::Less);
+        assert_eq!(natural_cmp("apple", "apple"), Ordering::Equal);
+    }
+
+    #[test]
+    fn natural_cmp_orders_embedded_numbers_by_value() {
+        assert_eq!(natural_cmp("file2", "file10"), Ordering::Less);
+        assert_eq!(natural_cmp("file10", "file9"), Ordering::Greater);
+        assert_eq!(natural_cmp("v1.10.0", "v1.9.3"), Ordering::Greater);
+        assert_eq!(natural_cmp("file", "file1"), Ordering::Less);
+        assert_eq!(
+            natural_cmp("img99999999999999999999", "img100000000000000000000"),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn natural_cmp_uses_leading_zeros_only_as_a_tie_break() {
+        assert_eq!(natural_cmp("a1", "a01"), Ordering::Less);
+        assert_eq!(natural_cmp("a01", "a2"), Ordering::Less);
+        assert_eq!(natural_cmp("a01b", "a1c"), Ordering::Less);
+        assert_eq!(natural_cmp("a001", "a01"), Ordering::Greater);
+    }
+
+    #[test]
+    fn format_bytes_scales_by_powers_of_1024() {
+        assert_eq!(format_bytes(0), "0.00 B");
+        assert_eq!(format_bytes(1023), "1023.00 B");
+        assert_eq!(format_bytes(1024), "1.00 KiB");
+        assert_eq!(format_bytes(1536), "1.50 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.00 MiB");
+        assert_eq!(format_bytes(3 << 30), "3.00 GiB");
+        assert_eq!(format_bytes(2 << 40), "2.00 TiB");
+    }
+}
@@ -0,0 +1,187 @@
+use super::{FileDirectory, Options, ThreadPool};
+use std::{
+    io::Result,
+    sync::{Arc, Mutex},
+};
+
+#[cfg(target_os = "linux")]
+use inotify::{Event, EventMask, Inotify, WatchDescriptor, WatchMask, Watches};
+#[cfg(target_os = "linux")]
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    io::ErrorKind,
+    sync::{
+        Weak,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+#[cfg(target_os = "linux")]
+const COALESCE_DELAY: Duration = Duration::from_millis(200);
+
+#[cfg(target_os = "linux")]
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Refresh,
+    Remove,
+}
+
+#[cfg(target_os = "linux")]
+type Changes = HashMap<(*const FileDirectory, String), (Arc<FileDirectory>, Change)>;
+
+pub struct Watcher {
+    #[cfg(target_os = "linux")]
+    watches: Mutex<Watches>,
+    #[cfg(target_os = "linux")]
+    directories: Mutex<HashMap<WatchDescriptor, Weak<FileDirectory>>>,
+    #[cfg(target_os = "linux")]
+    exhausted: AtomicBool,
+    warning: Mutex<Option<String>>,
+}
+
+impl Watcher {
+    pub fn take_warning(&self) -> Option<String> {
+        self.warning.lock().unwrap().take()
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Watcher {
+    pub fn start(thread_pool: Arc<ThreadPool>, options: Arc<Options>) -> Result<Arc<Self>> {
+        let mut inotify = Inotify::init()?;
+        let watcher = Arc::new(Self {
+            watches: Mutex::new(inotify.watches()),
+            directories: Mutex::new(HashMap::new()),
+            exhausted: AtomicBool::new(false),
+            warning: Mutex::new(None),
+        });
+
+        let watcher_clone = Arc::clone(&watcher);
+        thread::spawn(move || {
+            let watcher = watcher_clone;
+            let mut buffer = [0; 4096];
+            loop {
+                let mut changes = Changes::new();
+                match inotify.read_events_blocking(&mut buffer) {
+                    Ok(events) => events.for_each(|event| watcher.collect(event, &mut changes)),
+                    Err(e) => {
+                        watcher.warn(format!("Stopped watching for changes: {e}"));
+                        return;
+                    }
+                }
+
+                thread::sleep(COALESCE_DELAY);
+                while let Ok(events) = inotify.read_events(&mut buffer) {
+                    events.for_each(|event| watcher.collect(event, &mut changes));
+                }
+
+                thread_pool.wait();
+                for ((_, name), (directory, change)) in changes {
+                    match change {
+                        Change::Remove => thread_pool.remove_entry(&directory, &name),
+                        Change::Refresh => {
+                            if let Err(e) = thread_pool.refresh_entry(&options, &directory, name) {
+                                thread_pool.record_error(&directory, &e);
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        Ok(watcher)
+    }
+
+    fn warn(&self, warning: String) {
+        *self.warning.lock().unwrap() = Some(warning);
+    }
+
+    pub fn add(&self, directory: &Arc<FileDirectory>) {
+        if self.exhausted.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let mask = WatchMask::CREATE
+            | WatchMask::DELETE
+            | WatchMask::MODIFY
+            | WatchMask::CLOSE_WRITE
+            | WatchMask::ATTRIB
+            | WatchMask::MOVED_FROM
+            | WatchMask::MOVED_TO
+            | WatchMask::ONLYDIR;
+        match self.watches.lock().unwrap().add(&directory.path, mask) {
+            Ok(wd) => {
+                self.directories
+                    .lock()
+                    .unwrap()
+                    .insert(wd, Arc::downgrade(directory));
+            }
+            Err(e) if e.raw_os_error() == Some(libc::ENOSPC) => {
+                self.exhausted.store(true, Ordering::Relaxed);
+                self.warn(String::from(
+                    "Watch limit reached: only part of the tree is watched, press r to rescan",
+                ));
+            }
+            Err(e) if e.kind() == ErrorKind::OutOfMemory => {
+                self.exhausted.store(true, Ordering::Relaxed);
+                self.warn(format!("Stopped adding watches: {e}"));
+            }
+            Err(_) => {}
+        }
+    }
+
+    fn collect(&self, event: Event<&OsStr>, changes: &mut Changes) {
+        if event.mask.contains(EventMask::Q_OVERFLOW) {
+            self.warn(String::from("Missed filesystem changes: press r to rescan"));
+            return;
+        }
+        if event.mask.contains(EventMask::IGNORED) {
+            self.directories.lock().unwrap().remove(&event.wd);
+            return;
+        }
+
+        let Some(name) = event.name else {
+            return;
+        };
+        let change = if event
+            .mask
+            .intersects(EventMask::DELETE | EventMask::MOVED_FROM)
+        {
+            Change::Remove
+        } else if event
+            .mask
+            .intersects(EventMask::CREATE | EventMask::MOVED_TO)
+            || !event.mask.contains(EventMask::ISDIR)
+        {
+            Change::Refresh
+        } else {
+            return;
+        };
+        let Some(directory) = self
+            .directories
+            .lock()
+            .unwrap()
+            .get(&event.wd)
+            .and_then(Weak::upgrade)
+        else {
+            return;
+        };
+
+        let name = name.to_string_lossy().into_owned();
+        changes.insert((Arc::as_ptr(&directory), name), (directory, change));
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl Watcher {
+    pub fn start(_thread_pool: Arc<ThreadPool>, _options: Arc<Options>) -> Result<Arc<Self>> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "watching for changes is only supported on Linux",
+        ))
+    }
+
+    pub fn add(&self, _directory: &Arc<FileDirectory>) {}
+}
@@ -1,41 +1,39 @@
 mod app;
 
-use app::{App, ThreadPool};
-use std::{
-    env,
-    sync::{Arc, Mutex, Weak},
-    thread::available_parallelism,
-};
+use app::{App, ExportFormat, Options, ThreadPool};
+use std::sync::Arc;
 
 fn main() -> std::io::Result<()> {
-    let thread_count = available_parallelism()?.get();
-    let thread_pool = ThreadPool::new(thread_count * 2);
+    let options = Arc::new(Options::load()?);
+    let thread_pool = ThreadPool::new(options.thread_count()?);
+    if options.watch {
+        thread_pool.watch(&options)?;
+    }
+    let directory = match &options.import {
+        Some(path) => app::import_ncdu_from_path(path, &thread_pool)?,
+        None => Arc::clone(&thread_pool).scan(Arc::clone(&options))?,
+    };
+
+    if options.report.is_some() {
+        thread_pool.wait();
+        let errors = thread_pool.errors.lock().unwrap().len();
+        return app::print_report(&directory, &options, errors);
+    }
 
-    let current_dir_path = env::current_dir()?;
-    let mut current_dir_dev: Option<u64> = None;
+    if let Some(path) = &options.html {
+        thread_pool.wait();
+        return app::write_html_to_path(&directory, path, options.size_metric());
+    }
 
-    #[cfg(unix)]
-    {
-        use std::{fs, os::unix::fs::MetadataExt};
-        let metadata = fs::metadata(&current_dir_path)?;
-        current_dir_dev = Some(metadata.dev());
+    if let Some(path) = &options.export {
+        thread_pool.wait();
+        let format = options
+            .export_format
+            .unwrap_or_else(|| ExportFormat::from_path(path));
+        return app::export_to_path(&directory, path, format);
     }
 
-    let current_dir_name = env::current_exe()?
-        .file_name()
-        .unwrap()
-        .to_string_lossy()
-        .into_owned();
-
-    let mut app = App::new(
-        Arc::clone(&thread_pool),
-        Arc::clone(&thread_pool).scan_dir(
-            current_dir_dev,
-            current_dir_name,
-            current_dir_path,
-            Mutex::new(Weak::new()),
-        )?,
-    );
+    let mut app = App::new(Arc::clone(&thread_pool), Arc::clone(&options), directory);
 
     let mut terminal = ratatui::init();
     let app_result = app.run(&mut terminal);